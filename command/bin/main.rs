@@ -6,17 +6,96 @@
 // There is some error or other suggestions contact me : zzj01262022@163.com
 // Cargo run
 
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+
+use command::config;
 use command::env::init_env;
-use command::run::init_shell;
+use command::run::{init_shell, run_script, source_rc};
 use command::start::start_logo::start_logo;
 use command::root::SESSION;
+use command::start::state_code::{exit_code, MISSING_PARAMETERS, NOT_FOUND, STATUE_CODE};
+
+const USAGE: &str = "Usage: tiks [--norc] [-c COMMAND [NAME ARG...] | FILE [ARG...] | -]
+    tiks                 interactive shell, or read commands from a pipe
+    tiks --norc          interactive shell without ~/.Tiks/tiksrc
+    tiks -c COMMAND      run COMMAND and exit
+    tiks FILE            run the script FILE (`#!/usr/bin/env tiks`), ARG is $1...
+    tiks -               read commands from stdin";
 
 fn main() {
-        start_logo();
+        let mut args: Vec<String> = std::env::args().skip(1).collect();
+        // --norc: do not run ~/.Tiks/tiksrc
+        let norc = args.first().is_some_and(|a| a == "--norc");
+        if norc {
+            args.remove(0);
+        }
 
         // set os envirment path in Tiks
         init_env();
+        // ~/.Tiks/config.toml, the defaults stay when it is wrong
+        if let Err(e) = config::load() {
+            eprintln!("{}",e);
+        }
 
-        // init shell
-        init_shell(&mut *SESSION.lock().unwrap())
-}
\ No newline at end of file
+        // every mode exits with the last status
+        let status = match args.first().map(|a| a.as_str()){
+            // tiks -c "ls | grep a" [NAME ARG...], $0 is NAME, $1 is the first ARG
+            Some("-c") => match args.get(1){
+                Some(command) => {
+                    let mut session = SESSION.lock().unwrap();
+                    if let Some(name) = args.get(2) {
+                        session.shell_name = name.clone();
+                    }
+                    session.args = args.iter().skip(3).cloned().collect();
+                    run_script(command, &mut session)
+                }
+                None => {
+                    eprintln!("tiks: -c: option requires an argument\n{}",USAGE);
+                    MISSING_PARAMETERS
+                }
+            },
+            Some("-h") | Some("--help") => {
+                println!("{}",USAGE);
+                STATUE_CODE
+            }
+            Some(option) if option.starts_with('-') && option != "-" => {
+                eprintln!("tiks: unknown option {}\n{}",option,USAGE);
+                MISSING_PARAMETERS
+            }
+            // tiks script.tks [ARG...], the shebang line is a comment
+            Some(file) if file != "-" => match fs::read_to_string(file){
+                Ok(text) => {
+                    let mut session = SESSION.lock().unwrap();
+                    session.shell_name = file.to_string();
+                    session.args = args[1..].to_vec();
+                    run_script(&text, &mut session)
+                }
+                Err(e) => {
+                    eprintln!("tiks: {}: {}",file,e);
+                    NOT_FOUND
+                }
+            },
+            // echo ls | tiks, tiks - < file
+            _ if !args.is_empty() || !io::stdin().is_terminal() => {
+                let mut text = String::new();
+                match io::stdin().read_to_string(&mut text){
+                    Ok(_) => run_script(&text, &mut SESSION.lock().unwrap()),
+                    Err(e) => {
+                        eprintln!("tiks: stdin: {}",e);
+                        MISSING_PARAMETERS
+                    }
+                }
+            }
+            _ => {
+                start_logo();
+                let mut session = SESSION.lock().unwrap();
+                if !norc {
+                    source_rc(&mut session);
+                }
+                // init shell
+                init_shell(&mut session)
+            }
+        };
+        std::process::exit(exit_code(status))
+}
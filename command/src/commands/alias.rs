@@ -0,0 +1,126 @@
+// alias name='text': the parser puts text in place of name when it starts a command
+// kept in ~/.Tiks/aliases, one `alias name='text'` line each, read when Tiks starts;
+// aliases set by a sourced file (~/.Tiks/tiksrc too) are not saved, the file sets them again
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::parse::lexer::tokenize;
+use crate::root::SessionContext;
+use crate::start::error::TiksResult;
+use crate::start::state_code::{usage_error, NOT_IN_PATH, STATUE_CODE};
+
+
+// name -> text, sorted for `alias`
+pub type Aliases = BTreeMap<String, String>;
+
+pub fn alias_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".Tiks").join("aliases"))
+}
+
+// the saved aliases, none when there is no file yet
+pub fn load() -> Aliases {
+    alias_file().map(|file| load_from(&file)).unwrap_or_default()
+}
+
+// lines that are not `alias name=text` are skipped
+pub fn load_from(file: &Path) -> Aliases {
+    let mut aliases = Aliases::new();
+    let Ok(text) = fs::read_to_string(file) else {
+        return aliases;
+    };
+    for line in text.lines() {
+        let Ok(tokens) = tokenize(line) else {
+            continue;
+        };
+        let words: Vec<String> = tokens.iter().filter_map(|t| t.as_word()).map(|w| w.text()).collect();
+        if let [command, definition] = words.as_slice() {
+            if let (true, Some((name, value))) = (command == "alias", definition.split_once('=')) {
+                if valid_name(name) {
+                    aliases.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+    aliases
+}
+
+pub fn save_to(file: &Path, aliases: &Aliases) -> io::Result<()> {
+    let text: String = aliases.iter().map(|(name, value)| definition(name, value) + "\n").collect();
+    fs::write(file, text)
+}
+
+fn save(session_context: &SessionContext) -> io::Result<()> {
+    if session_context.sourcing > 0 {
+        return Ok(());
+    }
+    match alias_file() {
+        Some(file) => save_to(&file, &session_context.aliases),
+        None => Err(io::Error::new(io::ErrorKind::NotFound, "no home directory")),
+    }
+}
+
+// alias name='text', with ' written as '\''
+pub fn definition(name: &str, value: &str) -> String {
+    format!("alias {}='{}'", name, value.replace('\'', "'\\''"))
+}
+
+// no quotes, `$`, `/`, `=` or operators: the name must read back as one plain word
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| {
+        c.is_whitespace() || matches!(c, '=' | '/' | '$' | '`' | '\'' | '"' | '\\' | '|' | '&' | ';' | '<' | '>' | '(' | ')')
+    })
+}
+
+// alias: every alias, alias name: show it, alias name=text: set it
+pub fn alias(words: &[String], session_context: &mut SessionContext) -> TiksResult {
+    if words.is_empty() {
+        let list: Vec<String> = session_context.aliases.iter().map(|(n, v)| definition(n, v)).collect();
+        return Ok((STATUE_CODE, list.join("\n")));
+    }
+    let mut output = Vec::new();
+    let mut status = STATUE_CODE;
+    let mut changed = false;
+    for word in words {
+        match word.split_once('=') {
+            Some((name, value)) => {
+                if !valid_name(name) {
+                    return Err(usage_error(&format!("alias: `{}`: invalid alias name", name)));
+                }
+                session_context.aliases.insert(name.to_string(), value.to_string());
+                changed = true;
+            }
+            None => match session_context.aliases.get(word) {
+                Some(value) => output.push(definition(word, value)),
+                None => {
+                    crate::redirect::write_result((NOT_IN_PATH, format!("alias: {}: not found", word)));
+                    status = NOT_IN_PATH;
+                }
+            },
+        }
+    }
+    if changed {
+        save(session_context)?;
+    }
+    if status != STATUE_CODE {
+        crate::redirect::write_result((STATUE_CODE, output.join("\n")));
+        return Ok((status, String::new()));
+    }
+    Ok((status, output.join("\n")))
+}
+
+// unalias name..., unalias -a
+pub fn unalias(all: bool, names: &[String], session_context: &mut SessionContext) -> TiksResult {
+    if all {
+        session_context.aliases.clear();
+    } else if names.is_empty() {
+        return Err(usage_error("unalias: usage: unalias [-a] NAME..."));
+    }
+    let missing: Vec<&String> = names.iter().filter(|n| session_context.aliases.remove(*n).is_none()).collect();
+    save(session_context)?;
+    if let Some(name) = missing.first() {
+        return Err(usage_error(&format!("unalias: {}: not found", name)).with_code(NOT_IN_PATH));
+    }
+    Ok((STATUE_CODE, String::new()))
+}
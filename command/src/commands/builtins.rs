@@ -0,0 +1,417 @@
+// the builtins of Tiks, one registration each
+// add a command: a function in command.rs and one Builtin::new(...) here,
+// with_spec() tells which flags and arguments it takes
+use std::io::BufReader;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::priority::CommandPriority::{High, Low, Medium};
+use crate::process::process::ProcessManager;
+use crate::process::ps;
+use crate::redirect::{stdin, write_result};
+use crate::set::set::error_log;
+use crate::root::{decryption, Options, SessionContext};
+use crate::parse::parser::is_name;
+use crate::run::{source, Flow};
+use crate::set::version;
+use crate::start::error::TiksResult;
+use crate::start::state_code::{exit_code, missing_pattern, raw_status, usage_error, FALSE_STATUS, STATUE_CODE};
+
+use super::alias::{alias, unalias};
+use super::arg::{command_match, execute_program};
+use super::code::*;
+use super::command::*;
+use super::condition::{bracket, test};
+use super::expr::expr;
+use crate::expand::arith;
+use crate::config::config;
+use super::registry::{Builtin, Command};
+use super::spec::{Args, Spec};
+
+
+pub fn builtins() -> Vec<Arc<dyn Command>> {
+    let builtins = vec![
+        Builtin::new("pwd", "View current directory", |_, _| pwd()).with_priority(Low),
+        Builtin::new("ls", "View all files in the current directory", |args, s| match args.has("l") {
+            true => ll(s),
+            false => ls(args.has("a")),
+        })
+        .with_spec(Spec::new()
+            .flag('a', "all", "show hidden files too")
+            .flag('l', "long", "type, owner, size and time, like ll"))
+        .with_aliases(&["l"])
+        .with_priority(Low),
+        Builtin::new("ll", "View files with type, owner, size and time", |_, s| ll(s)),
+        Builtin::new("cd", "Change directory", |args, s| cd(&args.values[0], s))
+            .with_spec(Spec::new().arg("DIR"))
+            .with_priority(Medium),
+        Builtin::new("rm", "Delete directory or file", |args, _| each(&args.values, rm))
+            .with_spec(Spec::new().many("PATH"))
+            .with_priority(Medium)
+            .root_only(),
+        Builtin::new("touch", "Create a new file", |args, _| each(&args.values, touch))
+            .with_spec(Spec::new().many("FILE"))
+            .with_priority(Low)
+            .root_only(),
+        Builtin::new("mkdir", "Create a new directory", |args, _| each(&args.values, mkdir))
+            .with_spec(Spec::new()
+                // always recursive
+                .flag('p', "parents", "make the parent directories too")
+                .many("DIR"))
+            .with_priority(Low)
+            .root_only(),
+        // cat file, or read stdin: `< file`, `a | cat`
+        Builtin::new("cat", "View file only read", |args, _| match (args.get(0), stdin()) {
+            (Some(_), _) => each(&args.values, cat),
+            (None, Some(input)) => copy_or_read(input),
+            (None, None) => Err(missing_pattern()),
+        })
+        .with_spec(Spec::new().rest("FILE"))
+        .with_priority(Medium)
+        .root_only(),
+        Builtin::new("rn", "Rename directory or file", |args, _| rename(&args.values[0], &args.values[1]))
+            .with_spec(Spec::new().arg("FROM").arg("TO"))
+            .with_priority(Medium)
+            .root_only(),
+        Builtin::new("mv", "Move file's path", |args, _| rename(&args.values[0], &args.values[1]))
+            .with_spec(Spec::new().arg("FROM").arg("TO"))
+            .with_priority(Medium)
+            .root_only(),
+        Builtin::new("cp", "Copy file", |args, _| cp(&args.values[0], &args.values[1]))
+            .with_spec(Spec::new().arg("FROM").arg("TO")),
+        // grep pattern [file|text], or read stdin: `< file`, `a | grep x`
+        Builtin::new("grep", "Find lines of a file or stdin", |args, _| match (args.get(1), stdin()) {
+            (Some(file), _) => grep(&args.values[0], file),
+            (None, Some(input)) => grep_lines(&args.values[0], BufReader::new(input)),
+            (None, None) => Err(missing_pattern()),
+        })
+        .with_spec(Spec::new().arg("PATTERN").optional("FILE|TEXT"))
+        .with_priority(Medium),
+        Builtin::new("echo", "Print the arguments", |args, _| Ok(echo_print(args.values.join(" "))))
+            .with_spec(Spec::new().pass_through().rest("TEXT"))
+            .with_aliases(&["print"])
+            .with_priority(Low),
+        Builtin::new("python", "Run code in python", |args, _| python(Some(&args.values[0])))
+            .with_spec(Spec::new().arg("FILE"))
+            .with_priority(Medium),
+        Builtin::new("html", "Open html file", |args, _| html(Some(&args.values[0])))
+            .with_spec(Spec::new().arg("FILE"))
+            .with_aliases(&["web"])
+            .with_priority(Medium),
+        // tar -zxvf file... to.tar.gz, tar -xvf to.tar.gz...
+        Builtin::new("tar", "Compression and decompression", |args, _| {
+            match (args.has("z"), args.has("x"), args.values.split_last()) {
+                (true, _, Some((to, files))) if !files.is_empty() => zxvf(files, to),
+                (true, _, _) => Err(usage_error("tar: -z needs FILE and TO")),
+                (false, true, _) => each(&args.values, xvf),
+                _ => Err(usage_error("tar: one of -z or -x is needed")),
+            }
+        })
+        .with_spec(Spec::new()
+            .flag('z', "gzip", "compress every FILE into the last one, TO")
+            .flag('x', "extract", "decompress every FILE here")
+            .flag('v', "verbose", "show what is done")
+            .flag('f', "file", "read or write an archive file")
+            .many("FILE"))
+        .with_priority(Medium),
+        Builtin::new("history", "View past Commands", |_, _| history()).with_priority(Medium),
+        Builtin::new("time", "Show date and time", |_, _| Ok(get_time())),
+        Builtin::new("whoami", "Show current user", |_, s| whoami(s)).with_priority(Low),
+        Builtin::new("pd", "Check your password", |args, s| {
+            if let Some(password) = args.value("fix") {
+                s.user.revise_password(password)
+            } else if args.has("check") {
+                Ok((STATUE_CODE, decryption(s.user.password.clone())))
+            } else {
+                Err(usage_error("pd: one of -f or -c is needed"))
+            }
+        })
+        .with_spec(Spec::new()
+            .option('f', "fix", "PASSWORD", "fix your password")
+            .flag('c', "check", "show your password"))
+        .with_priority(Medium)
+        .root_only(),
+        Builtin::new("sudo", "Root", |args, s| match args.values.is_empty() {
+            true => sudo(s),
+            false => {
+                sudo(s)?;
+                let res = command_match(turn_command(args.values.clone()), s);
+                s.user_state.exit_root();
+                res
+            }
+        })
+        .with_spec(Spec::new().pass_through().rest("COMMAND")),
+        Builtin::new("exit", "Exit this process", |args, s| {
+            // exit [n], default is the last status
+            let code = args.int(0)
+                .map(|n| n as i32)
+                .unwrap_or(exit_code(s.last_status));
+            if s.subshell > 0 {
+                let status = raw_status(code.rem_euclid(256) as usize);
+                s.flow = Flow::Exit(status);
+                return Ok((status, String::new()));
+            }
+            if args.has("all") || !s.user_state.root.check_permission() {
+                std::process::exit(code);
+            }
+            s.user_state.exit_root();
+            Ok((STATUE_CODE, "Exit".to_string()))
+        })
+        .with_spec(Spec::new()
+            .flag('a', "all", "exit even in root mode")
+            .optional_int("N"))
+        .with_priority(Low),
+        Builtin::new("apt", "Install package or update tiks", |args, _| {
+            if let Some(package) = args.value("install") {
+                apt(package)
+            } else if args.has("update") {
+                match args.get(0) {
+                    Some(version) => update_new(version),
+                    None => update_lastest(),
+                }
+            } else {
+                Err(usage_error("apt: one of -i or -u is needed"))
+            }
+        })
+        .with_spec(Spec::new()
+            .option('i', "install", "PACKAGE", "install package")
+            .flag('u', "update", "update this version, or to VERSION")
+            .optional("VERSION"))
+        .with_priority(Medium),
+        Builtin::new("version", "Get your tiks version", |args, _| {
+            if args.has("list") {
+                version::get_version_list()
+            } else {
+                version::get_version_now()
+            }
+        })
+        .with_spec(Spec::new()
+            .flag('n', "now", "version now")
+            .flag('l', "list", "every version")),
+        Builtin::new("export", "Put variables in the environment of programs", |args, s| export(&args.values, s))
+            .with_spec(Spec::new().rest("NAME[=VALUE]")),
+        Builtin::new("unset", "Remove variables, or functions with -f", |args, s| unset(args.has("f"), &args.values, s))
+            .with_spec(Spec::new()
+                .flag('f', "", "remove functions")
+                .flag('v', "", "remove variables, the default")
+                .rest("NAME")),
+        Builtin::new("env", "Show the environment, or run a command with more variables", |args, s| env_command(&args.values, s))
+            .with_spec(Spec::new().pass_through().rest("NAME=VALUE... COMMAND")),
+        Builtin::new("printenv", "Show environment variables", |args, s| printenv(&args.values, s))
+            .with_spec(Spec::new().rest("NAME")),
+        Builtin::new("which", "Show the path of a program", |args, _| which(args.has("all"), &args.values))
+            .with_spec(Spec::new()
+                .flag('a', "all", "every match in PATH")
+                .many("NAME")),
+        Builtin::new("type", "Show what a name runs as", |args, s| type_of(&args.values, &s.functions))
+            .with_spec(Spec::new().many("NAME")),
+        // command -v name, command -V name, command name args: a function of that name is skipped
+        Builtin::new("command", "Run or describe a command", |args, s| {
+            if args.has("v") {
+                command_v(&args.values, &s.functions)
+            } else if args.has("V") {
+                type_of(&args.values, &s.functions)
+            } else {
+                execute_program(&args.values[0], &args.values[1..], s)
+            }
+        })
+        .with_spec(Spec::new()
+            .flag('v', "", "name of a builtin, path of a program")
+            .flag('V', "", "like type")
+            .many("COMMAND")),
+        Builtin::new("hash", "Show or forget the programs found in PATH", |args, _| hash(args.has("r"), &args.values))
+            .with_spec(Spec::new()
+                .flag('r', "", "forget every program")
+                .rest("NAME")),
+        Builtin::new("ps", "Show processes", |_, _| Ok((STATUE_CODE, ps()))).with_priority(High),
+        Builtin::new("kill", "Stop a process by pid", |args, _| {
+            let pid = args.int(0).unwrap_or_default().max(0) as usize;
+            Ok((STATUE_CODE, ProcessManager::new().kill(pid)))
+        })
+        .with_spec(Spec::new().int("PID"))
+        .with_priority(High),
+        Builtin::new("sleep", "Sleep for seconds", |args, _| {
+            let time = args.int(0).unwrap_or_default().max(0) as u64;
+            thread::sleep(Duration::from_secs(time));
+            Ok((STATUE_CODE, "sleep...".to_string()))
+        })
+        .with_spec(Spec::new().int("SECONDS"))
+        .with_priority(High),
+        Builtin::new("test", "Check files, text and numbers", |args, _| test(&args.values))
+            .with_spec(Spec::new().pass_through().rest("EXPR")),
+        Builtin::new("[", "Same as test, ends with ]", |args, _| bracket(&args.values))
+            .with_spec(Spec::new().pass_through().rest("EXPR")),
+        Builtin::new("let", "Compute each expression, fails when the last is 0", |args, s| let_command(&args.values, s))
+            .with_spec(Spec::new().pass_through().many("EXPR")),
+        Builtin::new("expr", "Compute an expression of separate arguments", |args, _| expr(&args.values))
+            .with_spec(Spec::new().pass_through().rest("ARG")),
+        Builtin::new("break", "Leave a for, while or until loop", |args, s| loop_flow(args, s, Flow::Break))
+            .with_spec(Spec::new().optional_int("N")),
+        Builtin::new("continue", "Next turn of a for, while or until loop", |args, s| loop_flow(args, s, Flow::Continue))
+            .with_spec(Spec::new().optional_int("N")),
+        Builtin::new("return", "Leave a function or a sourced file with a status", |args, s| {
+            if s.locals.is_empty() && s.sourcing == 0 {
+                return Err(usage_error("return: only meaningful in a function or a sourced file"));
+            }
+            // return without N keeps the last status
+            let status = args.int(0).map(|n| raw_status(n.rem_euclid(256) as usize)).unwrap_or(s.last_status);
+            s.flow = Flow::Return(status);
+            Ok((status, String::new()))
+        })
+        .with_spec(Spec::new().optional_int("N")),
+        Builtin::new("local", "Variables that end with the function", |args, s| local(&args.values, s))
+            .with_spec(Spec::new().rest("NAME[=VALUE]")),
+        // set -u, set +o nounset, set -- a b c
+        Builtin::new("set", "Shell options and positional arguments", |args, s| set_options(&args.values, s))
+            .with_spec(Spec::new().pass_through().rest("OPTION")),
+        Builtin::new("alias", "Show or set aliases, kept in ~/.Tiks/aliases", |args, s| alias(&args.values, s))
+            .with_spec(Spec::new().rest("NAME[=TEXT]")),
+        Builtin::new("unalias", "Remove aliases", |args, s| unalias(args.has("a"), &args.values, s))
+            .with_spec(Spec::new()
+                .flag('a', "", "remove every alias")
+                .rest("NAME")),
+        Builtin::new("source", "Run a file in this shell", |args, s| source(&args.values, s))
+            .with_aliases(&["."])
+            .with_spec(Spec::new().pass_through().rest("FILE [ARG...]")),
+        Builtin::new("config", "Show or change the settings in ~/.Tiks/config.toml", |args, s| config(&args.values, s))
+            .with_spec(Spec::new().pass_through().rest("list | get KEY | set KEY VALUE")),
+        // shopt -s nullglob, shopt -u dotglob, shopt
+        Builtin::new("shopt", "Turn shell options on or off", |args, s| {
+            let names: Vec<&str> = match args.values.is_empty() {
+                true => Options::names().to_vec(),
+                false => args.values.iter().map(|v| v.as_str()).collect(),
+            };
+            if let Some(bad) = names.iter().find(|n| s.options.get(n).is_none()) {
+                return Err(usage_error(&format!("shopt: {}: invalid shell option name", bad)));
+            }
+            match (args.has("s"), args.has("u")) {
+                (true, true) => Err(usage_error("shopt: cannot set and unset options at the same time")),
+                (false, false) => Ok((STATUE_CODE, s.options.list(&names))),
+                // shopt -s alone: the options that are on
+                (on, _) if args.values.is_empty() => {
+                    let names: Vec<&str> = names.into_iter().filter(|n| s.options.get(n) == Some(on)).collect();
+                    Ok((STATUE_CODE, s.options.list(&names)))
+                }
+                (on, _) => {
+                    for name in names {
+                        s.options.set(name, on);
+                    }
+                    Ok((STATUE_CODE, String::new()))
+                }
+            }
+        })
+        .with_spec(Spec::new()
+            .flag('s', "", "turn on")
+            .flag('u', "", "turn off")
+            .rest("NAME")),
+        Builtin::new("help", "Show commands, or the help of one", |args, _| help(&args.values))
+            .with_spec(Spec::new().optional("COMMAND")),
+        // test C define here
+        Builtin::new("hello_c", "Test the C build", |_, _| {
+            test_c();
+            Ok((STATUE_CODE, "test C ok...!".to_string()))
+        }),
+    ];
+    builtins.into_iter().map(|b| Arc::new(b) as Arc<dyn Command>).collect()
+}
+
+// cmd a b c: f for each argument, every output and error is shown,
+// the status is the last failure
+fn each(values: &[String], f: fn(&str) -> TiksResult) -> TiksResult {
+    if let [value] = values {
+        return f(value);
+    }
+    let mut status = STATUE_CODE;
+    for value in values {
+        let res = f(value).unwrap_or_else(|e| {
+            error_log(e.to_string());
+            e.into()
+        });
+        if res.0 != STATUE_CODE {
+            status = res.0;
+        }
+        write_result(res);
+    }
+    Ok((status, String::new()))
+}
+
+// local name=value...: the old values are put back by run::call_function
+fn local(words: &[String], session_context: &mut SessionContext) -> TiksResult {
+    let Some(frame) = session_context.locals.last_mut() else {
+        return Err(usage_error("local: only meaningful in a function"));
+    };
+    for word in words {
+        let (name, value) = word.split_once('=').unwrap_or((word, ""));
+        if !is_name(name) {
+            return Err(usage_error(&format!("local: `{}`: not a valid name", word)));
+        }
+        if !frame.iter().any(|(n, _)| n == name) {
+            frame.push((name.to_string(), session_context.vars.get(name).cloned()));
+        }
+        session_context.vars.insert(name.to_string(), value.to_string());
+    }
+    Ok((STATUE_CODE, String::new()))
+}
+
+// -o NAME / +o NAME turn an option on / off, -u is -o nounset, -f is -o noglob
+// without a NAME: list the options, after `--` the words are $1 $2 ...
+fn set_options(words: &[String], session_context: &mut SessionContext) -> TiksResult {
+    // set alone: every variable
+    if words.is_empty() {
+        let mut vars = session_context.environment();
+        vars.retain(|(name, _)| !session_context.vars.contains_key(name));
+        vars.extend(session_context.vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        vars.sort();
+        let list: Vec<String> = vars.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        return Ok((STATUE_CODE, list.join("\n")));
+    }
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        let on = word.starts_with('-');
+        let name = match word.as_str() {
+            "--" => {
+                session_context.args = words.cloned().collect();
+                break;
+            }
+            "-u" | "+u" => "nounset",
+            "-f" | "+f" => "noglob",
+            "-o" | "+o" => match words.next() {
+                Some(name) => name.as_str(),
+                None => return Ok((STATUE_CODE, session_context.options.list(&Options::names()))),
+            },
+            _ => return Err(usage_error(&format!("set: {}: invalid option", word))),
+        };
+        if !session_context.options.set(name, on) {
+            return Err(usage_error(&format!("set: {}: invalid option name", name)));
+        }
+    }
+    Ok((STATUE_CODE, String::new()))
+}
+
+// let 'i += 1' x=2*3: the same as $((...)) for each argument
+fn let_command(words: &[String], session_context: &mut SessionContext) -> TiksResult {
+    let mut value = 0;
+    for word in words {
+        value = arith::eval(word, session_context)?;
+    }
+    let status = if value != 0 { STATUE_CODE } else { FALSE_STATUS };
+    Ok((status, String::new()))
+}
+
+// break [n], continue [n]: the loops in run.rs read session.flow
+fn loop_flow(args: &Args, session_context: &mut SessionContext, flow: fn(usize) -> Flow) -> TiksResult {
+    let name = match flow(1) {
+        Flow::Continue(_) => "continue",
+        _ => "break",
+    };
+    if session_context.loop_depth == 0 {
+        return Err(usage_error(&format!("{}: only meaningful in a for, while or until loop", name)));
+    }
+    let n = args.int(0).unwrap_or(1);
+    if n < 1 {
+        return Err(usage_error(&format!("{}: {}: loop count out of range", name, n)));
+    }
+    session_context.flow = flow((n as usize).min(session_context.loop_depth));
+    Ok((STATUE_CODE, String::new()))
+}
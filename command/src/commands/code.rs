@@ -1,20 +1,23 @@
-use std::process::Command;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
 
-use crate::start::state_code::{run_code, run_code_er};
+use crate::start::state_code::{missing_pattern, permission_denied, raw_status, run_code, run_code_er};
+use crate::redirect;
+use crate::start::error::TiksResult;
 
 // run code use python ...
-pub fn python(file: Option<&str>) -> Result<(usize,String), std::io::Error> {
-    if file.is_none(){
-        eprintln!("Error: please provide a valid file")
-    }
+pub fn python(file: Option<&str>) -> TiksResult {
+    let file = file.ok_or_else(missing_pattern)?;
     let mut binding = Command::new("python3");
     let cmd = binding
-    .arg(file.unwrap());
+    .arg(file);
 
-    let s = cmd.spawn()?.wait();
+    let s = redirect::spawn(cmd);
 
     if s.is_err(){
-        return Ok(run_code_er());
+        return Err(run_code_er());
     }
 
     Ok(run_code())
@@ -22,19 +25,58 @@ pub fn python(file: Option<&str>) -> Result<(usize,String), std::io::Error> {
 
 
 // open web in html
-pub fn html(file: Option<&str>) -> Result<(usize,String),std::io::Error>{
+pub fn html(file: Option<&str>) -> TiksResult{
     match file {
         Some(html) => {
             let s = webbrowser::open(html);
             if s.is_err(){
-                return Ok(run_code_er());
+                return Err(run_code_er());
             }
         },
-        None => {
-            eprintln!("Error: Path is None. Please provide a valid file path or web.");
-        }
+        None => return Err(missing_pattern()),
     }
     Ok(run_code())
 }
 
-// others
\ No newline at end of file
+// others
+// program found in PATH, its exit code is the status;
+// env: exports and unsets of a ( ) on top of the process's environment
+pub fn external(name: &str, program: &Path, args: &[String], env: Option<&HashMap<String, Option<String>>>) -> TiksResult {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    for (key, value) in env.into_iter().flatten() {
+        match value {
+            Some(value) => cmd.env(key, value),
+            None => cmd.env_remove(key),
+        };
+    }
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.arg0(name);
+    }
+    #[cfg(windows)]
+    let _ = name;
+
+    let status = match redirect::spawn(&mut cmd) {
+        Ok(status) => status,
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => return Err(permission_denied()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok((exit_status(status), String::new()))
+}
+
+// killed by a signal: 128 + signal, like other shells
+fn exit_status(status: ExitStatus) -> usize {
+    if let Some(code) = status.code() {
+        return raw_status(code as usize);
+    }
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal as usize;
+        }
+    }
+    1
+}
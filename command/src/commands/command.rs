@@ -1,15 +1,15 @@
 use std::fs::File;
-use std::sync::{Mutex, RwLock};
+use std::sync::Mutex;
 use std::{env, fs};
-use std::io::{self, BufRead, Error, ErrorKind, Read, Write};
-use std::path::Path;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
 
 use async_std::task;
 use lazy_static::lazy_static;
 
 
 // whoami
-pub fn whoami(session_context: &mut SessionContext) -> io::Result<(usize,String)>{
+pub fn whoami(session_context: &mut SessionContext) -> TiksResult{
     let mut res = session_context.get_username();
     if session_context.user_state.root.check_permission(){
         res = "root".to_string()
@@ -19,43 +19,47 @@ pub fn whoami(session_context: &mut SessionContext) -> io::Result<(usize,String)
 }
 
 
-// help 
-pub fn help() -> String{
-    let help = format!(
-    "Usage: <command> [options] [arg]
-\0\x1B[32m Commands:
-    pwd     View current directory                         apt -i ..   Install package
-    ls      View all files in the current directory        history     View past Commands
-    cd      Change directory                               whoami  ||  apt -update version
-    rm      Delete directory or file                       rn          Rename directory or file  
-    touch   Create a new file                              mkdir       Create a new directory
-    cat     View file only read                            mv          Move file's path
-    python  Run code in python                             tar -zxvf:  Compression  
-    html    Open html file                                 tar -xvf:   Decompression
-    pd      Check your password                            sudo        Root
-    version Get your tiks version                          exit    Exit this process\0\x1B[0m\n"
-    );
-
-    help
+// help, help name
+pub fn help(names: &[String]) -> TiksResult{
+    if let Some(name) = names.first(){
+        return match registry::find(name){
+            Some(command) => Ok((STATUE_CODE,command.spec().help(command.name(),command.help()))),
+            None => Err(not_in_path(name)),
+        };
+    }
+    let mut output = String::from("Usage: <command> [options] [arg]\n\x1B[32m Commands:\n");
+    for command in registry::all(){
+        output.push_str(&format!("    {:<10}{}\n",command.name(),command.help()));
+    }
+    output.push_str("\x1B[0m");
+    Ok((STATUE_CODE,output))
 }
 
 
 // pwd
-pub fn pwd() -> io::Result<(usize,String)>{
+pub fn pwd() -> TiksResult{
     let path = env::current_dir().unwrap().as_path().display().to_string();
     Ok((STATUE_CODE,path))
 }
 
 
 // ls
-pub fn ls() -> io::Result<(usize,String)> { 
+pub fn ls(all: bool) -> TiksResult { 
     let dir_path = Path::new("./");
     let mut result = String::new();
+    // one name per line for `ls | grep` and `ls > file`
+    let plain = redirect::stdout().is_some();
 
     if dir_path.is_dir() {
         for entry in fs::read_dir(dir_path)? {
             let entry = entry?;
-            if entry.file_type()?.is_file() {
+            // .git, .Tiks ... only with -a
+            if !all && entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if plain {
+                result.push_str(&format!("{}\n", entry.file_name().to_string_lossy()));
+            } else if entry.file_type()?.is_file() {
                 result.push_str(&format!("{}    ", entry.file_name().into_string().unwrap()));
             } else {
                 result.push_str(&format!("\x1B[32m{}    \x1B[0m", entry.path().display()));
@@ -63,7 +67,7 @@ pub fn ls() -> io::Result<(usize,String)> {
         }
         Ok((STATUE_CODE,result))
     } else {
-        Err(Error::new(ErrorKind::NotFound, "Path is not a directory"))
+        Err(empty_dir())
     }
 }
 
@@ -71,7 +75,7 @@ pub fn ls() -> io::Result<(usize,String)> {
 // ll
 #[allow(unused_assignments)]
 #[allow(unused_mut)]
-pub fn ll(context: &SessionContext) -> io::Result<(usize,String)>{
+pub fn ll(context: &SessionContext) -> TiksResult{
     let dir_path = Path::new("./");
     let mut result = String::new();
     let dirs = fs::read_dir(dir_path)?;
@@ -160,20 +164,24 @@ pub fn history_push(command: String){
 }
 
 
-pub fn history() -> Result<(usize,String),Error>{
+pub fn history() -> TiksResult{
     let s = HISTROY.lock().unwrap();
-    for (i,c) in s.iter().enumerate(){
-        println!("{}: {}",i,c);
-    }
-
-    let res = String::new().trim().to_owned();
+    let res = s.iter()
+        .enumerate()
+        .map(|(i,c)| format!("{}: {}",i,c))
+        .collect::<Vec<_>>()
+        .join("\n");
     Ok((STATUE_CODE,res))
 }
 
 
-// cd
-pub fn cd(path: &str) -> Result<(usize,String),Error>{
+// cd; in a pipeline stage the directory is the whole shell's, so there it is only checked
+pub fn cd(path: &str, session_context: &SessionContext) -> TiksResult{
     let new_path = Path::new(path);
+    if session_context.stage{
+        fs::read_dir(new_path)?;
+        return Ok((STATUE_CODE,String::new()));
+    }
     env::set_current_dir(new_path)?;
 
     let res = format!("Successfully changed directory to {}.",path);
@@ -181,70 +189,10 @@ pub fn cd(path: &str) -> Result<(usize,String),Error>{
 }
 
 
-// new dir
-lazy_static!{
-    pub static ref DIR: RwLock<&'static str> = RwLock::new("");
-}
-
-
-pub fn turn_dir(command: String, dir: String) -> Result<(usize,String),Error>{
-    let mut dir_lock = DIR.write().unwrap();
-    *dir_lock = Box::leak(dir.into_boxed_str());
-
-    match command.as_str() {
-        "mkdir" => {
-            mkdir(&dir_lock)
-        },
-        "rm" => {
-            rm(&dir_lock)
-        },
-        "cd" =>{
-            cd(&dir_lock)
-        }
-        _ => {
-            Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Unsupported command",
-            ))
-        }
-    }
-}
-
-
-// new file 
-lazy_static! {
-    pub static ref FILE: RwLock<&'static str> = RwLock::new("");
-}
-
-
-pub fn turn_file(command: String,file: String) -> Result<(usize,String), Error> {
-    let mut file_lock = FILE.write().unwrap();
-    *file_lock = Box::leak(file.into_boxed_str());
-
-    match command.as_str() {
-        "touch" => {
-            touch(&file_lock)
-        },
-        "cat" => {
-            cat(&file_lock)
-        },
-        "rm" => {
-            rm(&file_lock)
-        }
-        _ =>{
-            Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Unsupported command",
-            ))
-        }
-    }
-}
-
-
 //touch
-pub fn touch(file: &str) -> Result<(usize,String),std::io::Error>{
+pub fn touch(file: &str) -> TiksResult{
     if file.is_empty(){
-        return Ok(empty_file());
+        return Err(empty_file());
     }
     let _ = fs::File::create(Path::new(file))?;
 
@@ -254,12 +202,13 @@ pub fn touch(file: &str) -> Result<(usize,String),std::io::Error>{
 
 
 // mkdir
-pub fn mkdir(dir: &str) -> Result<(usize,String),std::io::Error>{
+pub fn mkdir(dir: &str) -> TiksResult{
     if dir.is_empty(){
-        return Ok(empty_dir());
+        return Err(empty_dir());
     }
     let mut builder = fs::DirBuilder::new();
-    let _ = builder.recursive(true).create(Path::new(dir));
+    builder.recursive(true).create(Path::new(dir))
+        .map_err(|e| TiksError::io(format!("create {} failed",dir)).with_source(e))?;
 
     let res = format!("Successfully created {}",dir);
     Ok((STATUE_CODE,res))
@@ -267,19 +216,16 @@ pub fn mkdir(dir: &str) -> Result<(usize,String),std::io::Error>{
 
 
 // rm
-pub fn rm(file: &str) -> Result<(usize,String),std::io::Error>{
+pub fn rm(file: &str) -> TiksResult{
     if file.is_empty(){
-        return Ok(empty_file());
+        return Err(empty_file());
     }
     let filepath = Path::new(file);
-    match filepath.is_dir() {
-        true => {
-            let _ = fs::remove_dir(filepath);
-        },
-        false => {
-            let _ = fs::remove_file(filepath);
-        }
-    }
+    let removed = match filepath.is_dir() {
+        true => fs::remove_dir(filepath),
+        false => fs::remove_file(filepath),
+    };
+    removed.map_err(|e| TiksError::io(format!("remove {} failed",file)).with_source(e))?;
 
     let res = String::new().trim().to_owned();
     Ok((STATUE_CODE,res))
@@ -287,107 +233,123 @@ pub fn rm(file: &str) -> Result<(usize,String),std::io::Error>{
 
 
 // rn mv
-pub fn rename(source:&str,now:&str) -> std::io::Result<(usize,String)> {
+pub fn rename(source:&str,now:&str) -> TiksResult {
     if source.is_empty(){
-        return Ok(empty_file());
+        return Err(empty_file());
     }
-    let _ = fs::rename(source, now);
+    fs::rename(source, now).map_err(|e| TiksError::io(format!("rename {} to {} failed",source,now)).with_source(e))?;
     let res = String::new().trim().to_owned();
     Ok((STATUE_CODE,res))
 }
 
 
 // cat
-pub fn cat(file: &str) -> Result<(usize,String),Error>{
+pub fn cat(file: &str) -> TiksResult{
     if file.is_empty(){
-        return Ok(empty_file());
+        return Err(empty_file());
     }
-    let mut buffer = String::new();
-    let f = fs::File::open(Path::new(file)); 
-    let _ = f.unwrap().read_to_string(&mut buffer);
+    let f = fs::File::open(Path::new(file))?;
+    copy_or_read(f)
+}
 
-    Ok((STATUE_CODE,buffer))
+// stream into `> file` or `| b` without holding the whole text,
+// read it all only when it is shown in the terminal
+pub fn copy_or_read(mut reader: impl Read) -> TiksResult{
+    match redirect::stdout(){
+        Some(mut writer) => {
+            io::copy(&mut reader, &mut writer)?;
+            Ok((STATUE_CODE,String::new()))
+        }
+        None => {
+            let mut buffer = String::new();
+            reader.read_to_string(&mut buffer)?;
+            Ok((STATUE_CODE,buffer))
+        }
+    }
 }
 
 
 use crate::commands::apt::{download_package, find_package};
 use crate::priority::get_priority;
 use crate::set::set::file_create_time;
-use crate::run::run;
-use crate::start::state_code::{empty_dir, empty_file, env, missing_pattern, STATUE_CODE};
+use crate::run::{run, with_env, Flow};
+use crate::parse::parser::is_name;
+use super::arg::execute_program;
+use crate::start::state_code::{empty_dir, empty_file, env_err, missing_pattern, not_in_path, pipe_err, usage_error, NOT_IN_PATH, STATUE_CODE};
 use super::apt::{update, update_last};
 use crate::root::SessionContext;
+use crate::start::error::{TiksError, TiksResult};
 
 
 // apt -install  xxx
-pub fn apt(name: &str) -> io::Result<(usize,String)>{
+pub fn apt(name: &str) -> TiksResult{
     if name.is_empty(){
-        return Ok(missing_pattern());
+        return Err(missing_pattern());
     }
-    match find_package(name) {
-        Some(package) => {
-            if let Err(err) = download_package(&package) {
-                eprintln!("Error: {}", err);
-            }
-        },
-        None => {
-            eprintln!("Package {} not found.", name);
-        }
+    let package = match find_package(name){
+        Some(package) => package,
+        None => return Err(TiksError::not_found(format!("Package {} not found",name))),
+    };
+    match download_package(&package){
+        Ok(()) => Ok((STATUE_CODE,format!("Successfully download Package {}",name))),
+        Err(e) => Err(TiksError::network(format!("download Package {} failed",name)).with_source(e)),
     }
-
-    let res = format!("Successfully download Package {}",name);
-    Ok((STATUE_CODE,res))
 }
 
 
 // apt -update xxx
-pub fn update_new(version: &str) -> io::Result<(usize,String)>{
+pub fn update_new(version: &str) -> TiksResult{
     
-    match update(&version) {
+    match update(version) {
         Ok(_) => {
             let res = format!("Successfully Update version {}",version);
             Ok((STATUE_CODE,res))
         }
-        Err(_) => {
-            let err = format!("The current version is the latest one");
-            return Ok((STATUE_CODE,err));
-        },
+        Err(e) => Err(TiksError::network(format!("update to version {} failed",version)).with_source(e)),
     }
 
 }
 
 // update lastest
-#[allow(unused_must_use)]
-pub fn update_lastest() -> Result<(usize,String),std::io::Error>{
-    task::block_on(async {
-        update_last().await;
-    });
-    
-    return Ok((STATUE_CODE,"Successfully Update lastest version!".to_string()));
+pub fn update_lastest() -> TiksResult{
+    match task::block_on(update_last()){
+        Ok(()) => Ok((STATUE_CODE,"Successfully Update lastest version!".to_string())),
+        Err(e) => Err(TiksError::network("update to the lastest version failed").with_source(e)),
+    }
 }
 
 use tar::Archive;
 use flate2::read::GzDecoder;
 use flate2::Compression;
 use flate2::write::GzEncoder;
-use super::arg::{execute_command, execute_other_command, split, stdout_other, Commands};
+use super::arg::Commands;
+use super::registry;
+use crate::config;
+use crate::root::{Functions, UserRole};
+use crate::env::{clear_path_cache, find_in_path, hashed, hashed_all, search_path};
+use crate::parse::ast::{AndOr, Connector, Pipeline};
+use crate::redirect::{self, Input, Output, Streams};
+use std::thread;
 
 
-pub fn zxvf(file: &str, to: &str) -> Result<(usize,String),std::io::Error>{
-    if file.is_empty() || to.is_empty(){
-        return Ok(missing_pattern());
+pub fn zxvf(files: &[String], to: &str) -> TiksResult{
+    if files.iter().any(|f| f.is_empty()) || to.is_empty(){
+        return Err(missing_pattern());
     }
     let tar_gz = File::create(to)?;
     let enc = GzEncoder::new(tar_gz, Compression::default());
     let mut tar = tar::Builder::new(enc);
-    tar.append_file(file, &mut File::open(file)?)?;
+    for file in files{
+        tar.append_file(file, &mut File::open(file)?)?;
+    }
+    tar.into_inner()?.finish()?;
     Ok((STATUE_CODE,"Successfully Compression".to_string()))
 }
 
 
-pub fn xvf(to: &str) -> Result<(usize,String),std::io::Error>{
+pub fn xvf(to: &str) -> TiksResult{
     if to.is_empty(){
-        return Ok(missing_pattern());
+        return Err(missing_pattern());
     }
     let tar_gz = File::open(to)?;
     let tar = GzDecoder::new(tar_gz);
@@ -397,67 +359,36 @@ pub fn xvf(to: &str) -> Result<(usize,String),std::io::Error>{
 }
 
 
-// 重定向输出   > 
-#[allow(unused_variables)]
-#[allow(unused_assignments)]
-pub fn stdout_file(commands: Commands,session_context: &mut SessionContext) -> Result<(usize,String), std::io::Error>{
-    let mut command = commands.command.clone();
-    let mut option = String::new();
-    let mut arg = commands.arg.clone();
-    let mut file = String::new();
-    if command.is_empty(){
-        let (f,new_command) = stdout_other(&arg);
-        file = f;
-        let (command_v,option_v,arg_v) = split(new_command);
-        (command,option,arg) = (command_v,option_v,arg_v);
-    }else{
-        file = arg[arg.len()-1].clone();
-    }
-    let result = execute_command(&command, &option, &arg, session_context)?.1;
-    let mut file = File::create(file)?;
-    file.write(result.as_bytes())?;
-    Ok((STATUE_CODE,"write over!".to_string()))
-}
-
-
 // cp
 #[allow(unused_assignments)]
-pub fn cp(source:&str, to: &str) -> io::Result<(usize,String)>{
+pub fn cp(source:&str, to: &str) -> TiksResult{
     if source.is_empty() || to.is_empty(){
-        return Ok(missing_pattern());
+        return Err(missing_pattern());
     }
 
     let file = fs::read(source)?;
-
-    let result = fs::write(to, file);
-    let mut output = String::new();
-    match result.is_ok(){
-        true => {
-            output = format!("Successfully to copy {}",to);
-        },
-        false =>{
-            output = format!("Error: copy {} to {} failed",source,to);
-        }
+    match fs::write(to, file){
+        Ok(()) => Ok((STATUE_CODE,format!("Successfully to copy {}",to))),
+        Err(e) => Err(TiksError::io(format!("copy {} to {} failed",source,to)).with_source(e)),
     }
-
-    Ok((STATUE_CODE,output))
 }
 
 
 // sudo
 #[allow(unused_assignments)]
-pub fn sudo(session_context: &mut SessionContext)->io::Result<(usize,String)>{
+pub fn sudo(session_context: &mut SessionContext)->TiksResult{
     loop{
         let mut output = String::new();
         let user = session_context.get_username();
-        println!("[sudo] password for {}:",user);
-        let pd = rpassword::read_password().unwrap();
+        let _ = writeln!(redirect::err(),"[sudo] password for {}:",user);
+        // no terminal, e.g. `tiks -c 'sudo ls' </dev/null`
+        let pd = rpassword::read_password().map_err(|e| TiksError::io("sudo: can't read the password").with_source(e))?;
         let res = session_context.toggle_root(pd);
         if res.is_ok() {
-            output = format!("Sucessfully to change root");
+            output = "Sucessfully to change root".to_string();
             return Ok((STATUE_CODE,output));
         } else {
-            println!("Sorry, try again");
+            let _ = writeln!(redirect::err(),"Sorry, try again");
             continue;
         }
     }
@@ -473,31 +404,22 @@ pub fn get_time() -> (usize,String){
 }
 
 // grep
-pub fn grep(pattern:&str,arg: &str) -> io::Result<(usize,String)>{
+pub fn grep(pattern:&str,arg: &str) -> TiksResult{
     if arg.is_empty(){
-        return Ok(missing_pattern());
+        return Err(missing_pattern());
     }
 
     let mut output = String::new();
 
     if let Ok(file) = File::open(arg){
-        let reader = io::BufReader::new(file);
-
-        for line in reader.lines(){
-            let line = line?;
-            if line.contains(pattern){
-                let replaced_string = line.replace(pattern, &&format!("\x1b[31m{}\x1b[0m", pattern));
-                output.push_str(&replaced_string);
-                output.push_str("\n");
-            }
-        }
+        return grep_lines(pattern, io::BufReader::new(file));
     }else {
         let string_w = arg.split_whitespace();
         for i in string_w{
             if i.contains(pattern){
-                let replaced_string = i.replace(pattern, &&format!("\x1b[31m{}\x1b[0m", pattern));
+                let replaced_string = i.replace(pattern, &format!("\x1b[31m{}\x1b[0m", pattern) );
                 output.push_str(&replaced_string);
-                output.push_str("\n");
+                output.push('\n');
             }
         }
     }
@@ -510,112 +432,228 @@ pub fn grep(pattern:&str,arg: &str) -> io::Result<(usize,String)>{
     Ok((STATUE_CODE,output))
 }
 
+// grep a file or stdin line by line
+// matches are written as they are found when stdout is not the terminal
+pub fn grep_lines(pattern: &str, reader: impl BufRead) -> TiksResult{
+    let mut writer = redirect::stdout();
+    let mut output = String::new();
+    for line in reader.lines(){
+        let line = line?;
+        if !line.contains(pattern){
+            continue;
+        }
+        match writer.as_mut(){
+            Some(w) => {
+                // the reader of the pipe is gone
+                if writeln!(w, "{}", line).is_err(){
+                    break;
+                }
+            }
+            None => {
+                let replaced_string = line.replace(pattern, &format!("\x1b[31m{}\x1b[0m", pattern) );
+                output.push_str(&replaced_string);
+                output.push('\n');
+            }
+        }
+    }
+    Ok((STATUE_CODE,output))
+}
 
-// | pipe
-#[allow(unused_assignments)]
-pub fn pipe(command:Vec<String>) -> io::Result<(usize,String)>{
-    let spilt_vec = command.split(|pipe| pipe.as_str()=="|");
 
-    let mut output = String::new();
-    let mut last_result = Vec::new();
-
-    for i in spilt_vec{
-        let i = i.to_vec();
-        let commands = turn_command(i);
-        let (command,option,mut arg) = split(commands);
-        arg.append(&mut last_result);
-        let mut result = (0,String::new());
-        if arg.is_empty(){
-            result = execute_other_command(&command, &option, &last_result).expect("Error: ...");
-        }else{
-            result = execute_other_command(&command, &option, &arg).expect("Error: ...");
+const STAGE_STACK: usize = 8 * 1024 * 1024;
+
+// | pipe
+// every command but the last runs in its own thread with a copy of the session,
+// stdout of one is connected to stdin of the next by an OS pipe
+pub fn pipe(pipeline: &Pipeline,session_context: &mut SessionContext) -> (usize,String){
+    let (last, first) = match pipeline.commands.split_last(){
+        Some(c) => c,
+        None => return (STATUE_CODE,String::new()),
+    };
+    let mut handles = Vec::new();
+    let mut reader = None;
+
+    for command in first{
+        let (next_reader, writer) = match io::pipe(){
+            Ok(p) => p,
+            Err(e) => return pipe_err().with_source(e).into(),
+        };
+        let mut streams = match stage_streams(reader.take()){
+            Ok(s) => s,
+            Err(e) => return pipe_err().with_source(e).into(),
+        };
+        streams.stdout = Output::Pipe(writer);
+
+        let command = command.clone();
+        let mut session = session_context.clone();
+        session.subshell += 1;
+        // the threads share the directory and the environment: a stage keeps its changes
+        session.stage = true;
+        session.env.get_or_insert_with(Default::default);
+        // as much stack as the main thread, a stage may call functions
+        let stage = thread::Builder::new().stack_size(STAGE_STACK).spawn(move || {
+            // output went into the pipe or to stderr already
+            redirect::scoped(streams, || run(&command, &mut session));
+        });
+        match stage{
+            Ok(handle) => handles.push(handle),
+            Err(e) => return pipe_err().with_source(e).into(),
         }
-        last_result.push(result.1.clone());
-        output = result.1;
+        reader = Some(next_reader);
     }
 
-    Ok((STATUE_CODE,output))
+    // the last stage runs in this session, but `a | exit` ends only the stage
+    let stages = !first.is_empty();
+    session_context.subshell += stages as usize;
+    let mut output = match stage_streams(reader){
+        Ok(streams) => redirect::scoped(streams, || run(last, session_context)),
+        Err(e) => pipe_err().with_source(e).into(),
+    };
+    session_context.subshell -= stages as usize;
+    if let (true, Flow::Exit(n)) = (stages, session_context.flow){
+        output.0 = n;
+        session_context.flow = Flow::Normal;
+        session_context.last_status = n;
+    }
+    for handle in handles{
+        let _ = handle.join();
+    }
+    output
+}
+
+// current streams, reading from the previous command when there is one
+fn stage_streams(reader: Option<io::PipeReader>) -> io::Result<Streams>{
+    let mut streams = redirect::current()?;
+    if let Some(reader) = reader{
+        streams.stdin = Input::Pipe(reader);
+    }
+    Ok(streams)
 }
 
-// &&
-pub fn and(command:Vec<String>,session_context: &mut SessionContext) -> Vec<(usize,String)>{
+// && runs after success, || runs after failure
+pub fn and(and_or: &AndOr,session_context: &mut SessionContext) -> Vec<(usize,String)>{
     let mut output:Vec<_> = Vec::new();
-    let commands = command.split(|x| x=="&&");
-    for c in commands{
-        let v = c.to_vec();
-        let r = run(v, session_context);
-        output.push(r)
+    let first = pipe(&and_or.first, session_context);
+    let mut status = first.0;
+    output.push(first);
+    for (connector,pipeline) in &and_or.rest{
+        // `break && ...`
+        if session_context.flow != Flow::Normal{
+            break;
+        }
+        let skip = match connector{
+            Connector::And => status!=STATUE_CODE,
+            Connector::Or => status==STATUE_CODE,
+        };
+        if skip{
+            continue;
+        }
+        let res = pipe(pipeline, session_context);
+        status = res.0;
+        output.push(res);
     }
     output
 }
 
 // &
-pub fn priority_run(command:Vec<String>,session_context: &mut SessionContext) -> Vec<(usize,String)>{
+pub fn priority_run(commands: &[&AndOr],session_context: &mut SessionContext) -> Vec<(usize,String)>{
     let mut output:Vec<_> = Vec::new();
-    let commands = command.split(|x| x=="&");
-    let mut save_command = Vec::new();
-    for c in commands{
-        let v = c.to_vec();
-        save_command.push(v);
-    }
+    let mut save_command = commands.to_vec();
 
-    save_command.sort_by_key(|c| -(get_priority(&c[0]).as_number() as i32));
+    save_command.sort_by_key(|c| -(get_priority(&c.name()).as_number() as i32));
 
     for c in save_command{
-        let r = run(c, session_context);
-        output.push(r)
+        output.extend(and(c, session_context));
     }
     output
 }
 
-fn get_env(id: String) -> String{
-    let path = id.replace("$", "");
-    if let Some(val) = env::var(&path).ok(){
-        val
-    }else{
-        panic!("Error: {} is not exist",path)
+// the value is kept as it is, `:` and all
+fn set_env_command(key: &str, value: &str, session_context: &mut SessionContext) -> io::Result<()>{
+    if value.contains('\0'){
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the value has a NUL byte"));
     }
+    session_context.set_env(key, value);
+    Ok(())
 }
 
-use std::path::PathBuf;
-fn set_env_command(key: &str, path: &str) -> io::Result<()>{
-    let mut path_set = Vec::new();
-    let s = PathBuf::from(format!("{}",path));
-
-    path_set.push(s);
-
-    let new_path = env::join_paths(path_set).expect("Failed join paths");
-
-    env::set_var(key, new_path);
-    Ok(())
+// export NAME=VALUE, export NAME: the variable goes to the environment of programs
+pub fn export(words: &[String], session_context: &mut SessionContext) -> TiksResult{
+    if words.is_empty(){
+        let list: Vec<String> = session_context.environment().iter().map(|(k, v)| format!("export {}={}", k, v)).collect();
+        return Ok((STATUE_CODE, list.join("\n")));
+    }
+    for word in words{
+        let (name, value) = match word.split_once('='){
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (word.as_str(), None),
+        };
+        if !is_name(name){
+            return Err(usage_error(&format!("export: `{}`: not a valid name", word)));
+        }
+        // a shell variable leaves the session's list for the environment
+        let var = session_context.vars.remove(name);
+        if let Some(value) = value.or(var){
+            set_env_command(name, &value, session_context).map_err(|e| env_err(name).with_source(e))?;
+        }
+    }
+    Ok((STATUE_CODE, String::new()))
 }
 
-pub fn set(key: &str, path: &str) -> io::Result<(usize,String)>{
-    match set_env_command(key, path){
-        Ok(())=>{
-            return Ok(env());
-        },
-        Err(_) =>{
-            let error_str = format!("Can't set env {}",key);
-            return Ok((108,error_str));
+// unset NAME..., unset -f NAME...
+pub fn unset(functions: bool, names: &[String], session_context: &mut SessionContext) -> TiksResult{
+    for name in names{
+        if functions{
+            session_context.functions.remove(name);
+            continue;
         }
+        if !is_name(name){
+            return Err(usage_error(&format!("unset: `{}`: not a valid name", name)));
+        }
+        session_context.unset_var(name);
+    }
+    Ok((STATUE_CODE, String::new()))
+}
+
+// env: the environment, env NAME=VALUE... COMMAND: run it with these too
+pub fn env_command(words: &[String], session_context: &mut SessionContext) -> TiksResult{
+    let count = words.iter().take_while(|w| w.split_once('=').is_some_and(|(name, _)| is_name(name))).count();
+    let assigns: Vec<(String,String)> = words[..count].iter()
+        .filter_map(|w| w.split_once('=').map(|(n, v)| (n.to_string(), v.to_string())))
+        .collect();
+    let command = words[count..].to_vec();
+    with_env(&assigns, session_context, |s| {
+        if command.is_empty(){
+            let list: Vec<String> = s.environment().iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            return Ok((STATUE_CODE, list.join("\n")));
+        }
+        execute_program(&command[0], &command[1..], s)
+    })
+}
+
+// printenv NAME...: the values of these, status 1 when one is not set
+pub fn printenv(names: &[String], session_context: &SessionContext) -> TiksResult{
+    if names.is_empty(){
+        let list: Vec<String> = session_context.environment().iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        return Ok((STATUE_CODE, list.join("\n")));
+    }
+    let values: Vec<String> = names.iter().filter_map(|name| session_context.env_var(name)).collect();
+    if values.len() == names.len(){
+        return Ok((STATUE_CODE, values.join("\n")));
     }
+    // the values found still go to stdout
+    redirect::write_result((STATUE_CODE, values.join("\n")));
+    Ok((NOT_IN_PATH, String::new()))
 }
 
+// $VAR is already expanded, print as it is
 pub fn echo_print<T: std::fmt::Display + From<String>>(output: T) -> (usize,T){
-    let var = format!("{}", output);
-    if var.contains("$") {
-        let val = get_env(var);
-        return (STATUE_CODE, val.into());
-    }else{
-        (STATUE_CODE, output)
-    }
+    (STATUE_CODE, output)
 }
 
 // turn vec<_> to Commands
 pub fn turn_command(v: Vec<String>) -> Commands{
-    let ouput = Commands::new(v);
-    ouput
+    Commands::new(v)
 }
 
 
@@ -629,4 +667,122 @@ pub fn test_c(){
     unsafe{
         hello()
     }
-}
\ No newline at end of file
+}
+
+
+// what a name runs as
+pub enum Resolved{
+    Function,
+    Builtin(UserRole),  // role needed
+    Hashed(PathBuf),
+    File(PathBuf),
+}
+
+// in the order they are run: functions, builtins, programs
+pub fn resolve(name: &str, functions: &Functions) -> Option<Resolved>{
+    if functions.contains_key(name){
+        return Some(Resolved::Function);
+    }
+    if registry::find(name).is_some(){
+        // root.commands, as the permission check
+        let role = if config::root_only(name) { UserRole::Admin } else { UserRole::User };
+        return Some(Resolved::Builtin(role));
+    }
+    if let Some(path) = hashed(name){
+        return Some(Resolved::Hashed(path));
+    }
+    match name.contains('/'){
+        true => find_in_path(name),
+        false => search_path(name, false).pop(),
+    }.map(Resolved::File)
+}
+
+// which [-a] name...
+pub fn which(all: bool, names: &[String]) -> TiksResult{
+    let mut output = Vec::new();
+    let mut missing = Vec::new();
+    for name in names{
+        let found = match name.contains('/'){
+            true => find_in_path(name).into_iter().collect(),
+            false => search_path(name, all),
+        };
+        if found.is_empty(){
+            missing.push(name.as_str());
+        }
+        output.extend(found.iter().map(|p| p.display().to_string()));
+    }
+    if missing.is_empty(){
+        return Ok((STATUE_CODE,output.join("\n")));
+    }
+    // the paths found still go to stdout
+    redirect::write_result((STATUE_CODE, output.join("\n")));
+    Err(not_in_path(&missing.join(", ")))
+}
+
+// type name...
+pub fn type_of(names: &[String], functions: &Functions) -> TiksResult{
+    let mut output = Vec::new();
+    let mut missing = Vec::new();
+    for name in names{
+        output.push(match resolve(name, functions){
+            Some(Resolved::Function) => format!("{} is a function",name),
+            Some(Resolved::Builtin(UserRole::Admin)) => format!("{} is a tiks builtin (root only)",name),
+            Some(Resolved::Builtin(UserRole::User)) => format!("{} is a tiks builtin",name),
+            Some(Resolved::Hashed(path)) => format!("{} is hashed ({})",name,path.display()),
+            Some(Resolved::File(path)) => format!("{} is {}",name,path.display()),
+            None => {
+                missing.push(name.as_str());
+                continue;
+            }
+        });
+    }
+    if missing.is_empty(){
+        return Ok((STATUE_CODE,output.join("\n")));
+    }
+    // found names still go to stdout, like which
+    redirect::write_result((STATUE_CODE, output.join("\n")));
+    Err(not_in_path(&missing.join(", ")))
+}
+
+// command -v name...: the name of a function or builtin, the path of a program
+pub fn command_v(names: &[String], functions: &Functions) -> TiksResult{
+    let mut status = STATUE_CODE;
+    let mut output = Vec::new();
+    for name in names{
+        match resolve(name, functions){
+            Some(Resolved::Function) | Some(Resolved::Builtin(_)) => output.push(name.clone()),
+            Some(Resolved::Hashed(path)) | Some(Resolved::File(path)) => output.push(path.display().to_string()),
+            None => status = NOT_IN_PATH,
+        }
+    }
+    if status == STATUE_CODE{
+        return Ok((STATUE_CODE,output.join("\n")));
+    }
+    // like bash, a name not found prints nothing
+    redirect::write_result((STATUE_CODE, output.join("\n")));
+    Ok((status,String::new()))
+}
+
+// hash: list, hash -r: forget all, hash name...: look up now
+pub fn hash(reset: bool, names: &[String]) -> TiksResult{
+    if reset{
+        clear_path_cache();
+        return Ok((STATUE_CODE,String::new()));
+    }
+    if names.is_empty(){
+        let table = hashed_all();
+        if table.is_empty(){
+            return Ok((STATUE_CODE,"hash: hash table empty".to_string()));
+        }
+        let output = table.iter()
+            .map(|(name,path)| format!("{}\t{}",name,path.display()))
+            .collect::<Vec<_>>();
+        return Ok((STATUE_CODE,output.join("\n")));
+    }
+    for name in names{
+        if find_in_path(name).is_none(){
+            return Err(not_in_path(name));
+        }
+    }
+    Ok((STATUE_CODE,String::new()))
+}
@@ -0,0 +1,166 @@
+// test EXPR, [ EXPR ]: status 0 when true, 1 when false
+//
+//  -e -f -d -r -w -x -s -L FILE    file exists, is a file, a dir, ...
+//  -z -n TEXT                      empty, not empty
+//  A = B, A != B, A < B, A > B     text
+//  A -eq -ne -lt -le -gt -ge B     numbers
+//  A -nt -ot B                     file newer, older
+//  ! EXPR, EXPR -a EXPR, EXPR -o EXPR, ( EXPR )
+use std::fs;
+use std::path::Path;
+
+use crate::start::error::{TiksError, TiksResult};
+use crate::start::state_code::{usage_error, NOT_IN_PATH, STATUE_CODE};
+
+
+pub fn test(args: &[String]) -> TiksResult{
+    let mut expr = Expr { args, pos: 0 };
+    // nothing is false, one word is true when not empty
+    let res = match args.len(){
+        0 => false,
+        _ => {
+            let res = expr.or()?;
+            if let Some(extra) = args.get(expr.pos){
+                return Err(test_err(&format!("unexpected `{}`", extra)));
+            }
+            res
+        }
+    };
+    Ok((if res { STATUE_CODE } else { NOT_IN_PATH }, String::new()))
+}
+
+// [ EXPR ]
+pub fn bracket(args: &[String]) -> TiksResult{
+    match args.split_last(){
+        Some((last, expr)) if last == "]" => test(expr),
+        _ => Err(usage_error("[: missing `]`")),
+    }
+}
+
+fn test_err(msg: &str) -> TiksError{
+    usage_error(&format!("test: {}", msg))
+}
+
+
+struct Expr<'a>{
+    args: &'a [String],
+    pos: usize,
+}
+
+impl Expr<'_>{
+    fn peek(&self, offset: usize) -> Option<&str>{
+        self.args.get(self.pos + offset).map(|s| s.as_str())
+    }
+
+    fn next(&mut self) -> Result<&str, TiksError>{
+        let arg = self.args.get(self.pos).ok_or_else(|| test_err("argument expected"))?;
+        self.pos += 1;
+        Ok(arg)
+    }
+
+    fn or(&mut self) -> Result<bool, TiksError>{
+        let mut res = self.and()?;
+        while self.peek(0) == Some("-o"){
+            self.pos += 1;
+            res |= self.and()?;
+        }
+        Ok(res)
+    }
+
+    fn and(&mut self) -> Result<bool, TiksError>{
+        let mut res = self.not()?;
+        while self.peek(0) == Some("-a"){
+            self.pos += 1;
+            res &= self.not()?;
+        }
+        Ok(res)
+    }
+
+    fn not(&mut self) -> Result<bool, TiksError>{
+        // `! = x` compares "!"
+        if self.peek(0) == Some("!") && self.peek(1).is_some() && !is_binary(self.peek(1)){
+            self.pos += 1;
+            return Ok(!self.not()?);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<bool, TiksError>{
+        if self.peek(0) == Some("(") && !is_binary(self.peek(1)){
+            self.pos += 1;
+            let res = self.or()?;
+            if self.next()? != ")"{
+                return Err(test_err("missing `)`"));
+            }
+            return Ok(res);
+        }
+        // A op B
+        if is_binary(self.peek(1)) && self.peek(2).is_some(){
+            let left = self.next()?.to_string();
+            let op = self.next()?.to_string();
+            let right = self.next()?.to_string();
+            return binary(&left, &op, &right);
+        }
+        let arg = self.next()?.to_string();
+        // -f FILE, unless it is the last word: `test -f` is true
+        if arg.len() == 2 && arg.starts_with('-') && self.peek(0).is_some(){
+            if let Some(res) = unary(&arg, self.peek(0).unwrap_or_default()){
+                self.pos += 1;
+                return Ok(res);
+            }
+        }
+        Ok(!arg.is_empty())
+    }
+}
+
+fn is_binary(arg: Option<&str>) -> bool{
+    matches!(arg, Some("=" | "==" | "!=" | "<" | ">" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge" | "-nt" | "-ot"))
+}
+
+fn unary(op: &str, arg: &str) -> Option<bool>{
+    let path = Path::new(arg);
+    let meta = fs::metadata(path);
+    Some(match op{
+        "-z" => arg.is_empty(),
+        "-n" => !arg.is_empty(),
+        "-e" => meta.is_ok(),
+        "-f" => meta.map(|m| m.is_file()).unwrap_or(false),
+        "-d" => meta.map(|m| m.is_dir()).unwrap_or(false),
+        "-s" => meta.map(|m| m.len() > 0).unwrap_or(false),
+        "-L" | "-h" => fs::symlink_metadata(path).map(|m| m.file_type().is_symlink()).unwrap_or(false),
+        "-r" => fs::File::open(path).is_ok(),
+        "-w" => meta.map(|m| !m.permissions().readonly()).unwrap_or(false),
+        "-x" => executable(path),
+        _ => return None,
+    })
+}
+
+fn binary(left: &str, op: &str, right: &str) -> Result<bool, TiksError>{
+    let number = |s: &str| s.trim().parse::<i64>().map_err(|_| test_err(&format!("integer expected, not `{}`", s)));
+    let modified = |s: &str| fs::metadata(s).and_then(|m| m.modified()).ok();
+    Ok(match op{
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        ">" => left > right,
+        "-eq" => number(left)? == number(right)?,
+        "-ne" => number(left)? != number(right)?,
+        "-lt" => number(left)? < number(right)?,
+        "-le" => number(left)? <= number(right)?,
+        "-gt" => number(left)? > number(right)?,
+        "-ge" => number(left)? >= number(right)?,
+        "-nt" => matches!((modified(left), modified(right)), (Some(a), Some(b)) if a > b),
+        _ => matches!((modified(left), modified(right)), (Some(a), Some(b)) if a < b),
+    })
+}
+
+#[cfg(not(windows))]
+fn executable(path: &Path) -> bool{
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn executable(path: &Path) -> bool{
+    path.is_file()
+}
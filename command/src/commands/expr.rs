@@ -0,0 +1,146 @@
+// expr: every operator and operand is its own argument
+//
+//   a | b     a if it is not null or 0, else b
+//   a & b     a if neither is null or 0, else 0
+//   = != < <= > >=    as numbers when both are integers, else as text
+//   + - * / %         integers only
+//   ( )  length STRING
+//
+// the status is 1 when the result is null or 0
+use crate::start::error::{TiksError, TiksResult};
+use crate::start::state_code::{usage_error, FALSE_STATUS, STATUE_CODE};
+
+
+pub fn expr(args: &[String]) -> TiksResult {
+    if args.is_empty() {
+        return Err(usage_error("expr: missing operand"));
+    }
+    let mut parser = Parser { args, pos: 0 };
+    let value = parser.or()?;
+    if let Some(extra) = args.get(parser.pos) {
+        return Err(syntax(&format!("unexpected argument `{}`", extra)));
+    }
+    let status = if is_null(&value) { FALSE_STATUS } else { STATUE_CODE };
+    if status != STATUE_CODE {
+        // still printed to stdout
+        crate::redirect::write_result((STATUE_CODE, value));
+        return Ok((status, String::new()));
+    }
+    Ok((status, value))
+}
+
+fn syntax(msg: &str) -> TiksError {
+    usage_error(&format!("expr: syntax error: {}", msg))
+}
+
+fn is_null(value: &str) -> bool {
+    value.is_empty() || value.parse::<i64>() == Ok(0)
+}
+
+fn int(value: &str) -> Result<i64, TiksError> {
+    value.parse::<i64>().map_err(|_| usage_error(&format!("expr: non-integer argument `{}`", value)))
+}
+
+struct Parser<'a> {
+    args: &'a [String],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn eat(&mut self, ops: &[&str]) -> Option<String> {
+        let op = self.args.get(self.pos).filter(|a| ops.contains(&a.as_str()))?.clone();
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn or(&mut self) -> Result<String, TiksError> {
+        let mut lhs = self.and()?;
+        while self.eat(&["|"]).is_some() {
+            let rhs = self.and()?;
+            if is_null(&lhs) {
+                lhs = if is_null(&rhs) { "0".to_string() } else { rhs };
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<String, TiksError> {
+        let mut lhs = self.compare()?;
+        while self.eat(&["&"]).is_some() {
+            let rhs = self.compare()?;
+            if is_null(&lhs) || is_null(&rhs) {
+                lhs = "0".to_string();
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn compare(&mut self) -> Result<String, TiksError> {
+        let mut lhs = self.sum()?;
+        while let Some(op) = self.eat(&["=", "==", "!=", "<", "<=", ">", ">="]) {
+            let rhs = self.sum()?;
+            let order = match (lhs.parse::<i64>(), rhs.parse::<i64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => lhs.cmp(&rhs),
+            };
+            let yes = match op.as_str() {
+                "=" | "==" => order.is_eq(),
+                "!=" => order.is_ne(),
+                "<" => order.is_lt(),
+                "<=" => order.is_le(),
+                ">" => order.is_gt(),
+                _ => order.is_ge(),
+            };
+            lhs = (yes as i64).to_string();
+        }
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<String, TiksError> {
+        let mut lhs = self.product()?;
+        while let Some(op) = self.eat(&["+", "-"]) {
+            let (a, b) = (int(&lhs)?, int(&self.product()?)?);
+            let value = if op == "+" { a.checked_add(b) } else { a.checked_sub(b) };
+            lhs = value.ok_or_else(|| usage_error("expr: integer overflow"))?.to_string();
+        }
+        Ok(lhs)
+    }
+
+    fn product(&mut self) -> Result<String, TiksError> {
+        let mut lhs = self.primary()?;
+        while let Some(op) = self.eat(&["*", "/", "%"]) {
+            let (a, b) = (int(&lhs)?, int(&self.primary()?)?);
+            if op != "*" && b == 0 {
+                return Err(usage_error("expr: division by zero"));
+            }
+            let value = match op.as_str() {
+                "*" => a.checked_mul(b),
+                "/" => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            lhs = value.ok_or_else(|| usage_error("expr: integer overflow"))?.to_string();
+        }
+        Ok(lhs)
+    }
+
+    fn primary(&mut self) -> Result<String, TiksError> {
+        let Some(arg) = self.args.get(self.pos).cloned() else {
+            return Err(syntax("missing argument"));
+        };
+        self.pos += 1;
+        match arg.as_str() {
+            "(" => {
+                let value = self.or()?;
+                if self.eat(&[")"]).is_none() {
+                    return Err(syntax("missing `)`"));
+                }
+                Ok(value)
+            }
+            "length" if self.pos < self.args.len() => {
+                self.pos += 1;
+                Ok(self.args[self.pos - 1].chars().count().to_string())
+            }
+            _ => Ok(arg),
+        }
+    }
+}
@@ -1,4 +1,10 @@
+pub mod alias;
 pub mod arg;
 mod code;
 pub mod apt;
-pub mod command;
\ No newline at end of file
+pub mod command;
+pub mod registry;
+pub mod spec;
+pub mod condition;
+pub mod expr;
+mod builtins;
\ No newline at end of file
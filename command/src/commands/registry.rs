@@ -0,0 +1,184 @@
+// every command of Tiks is registered here once
+// dispatch, help, get_similar, get_priority and the default root.commands
+// are all read from the registry
+use std::sync::{Arc, RwLock};
+
+use lazy_static::lazy_static;
+
+use crate::config;
+use crate::priority::CommandPriority;
+use crate::root::{SessionContext, UserRole};
+use crate::start::error::TiksResult;
+use crate::start::state_code::STATUE_CODE;
+
+use super::builtins::builtins;
+use super::spec::{Args, Spec};
+
+
+pub trait Command: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    fn priority(&self) -> CommandPriority {
+        CommandPriority::Unknow
+    }
+
+    // UserRole::Admin: only after sudo
+    fn role(&self) -> UserRole {
+        UserRole::User
+    }
+
+    fn help(&self) -> &str;
+
+    // flags and arguments, checked before execute; by default every word goes to the command
+    fn spec(&self) -> Spec {
+        Spec::new().pass_through().rest("ARG")
+    }
+
+    fn execute(&self, args: &Args, session_context: &mut SessionContext) -> TiksResult;
+}
+
+
+type Run = fn(&Args, &mut SessionContext) -> TiksResult;
+
+// a command made of a function, for the builtins table
+pub struct Builtin {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    priority: CommandPriority,
+    role: UserRole,
+    help: &'static str,
+    spec: Spec,
+    run: Run,
+}
+
+impl Builtin {
+    pub fn new(name: &'static str, help: &'static str, run: Run) -> Builtin {
+        Builtin {
+            name,
+            aliases: &[],
+            priority: CommandPriority::Unknow,
+            role: UserRole::User,
+            help,
+            spec: Spec::new(),
+            run,
+        }
+    }
+
+    pub fn with_spec(mut self, spec: Spec) -> Builtin {
+        self.spec = spec;
+        self
+    }
+
+    pub fn with_aliases(mut self, aliases: &'static [&'static str]) -> Builtin {
+        self.aliases = aliases;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: CommandPriority) -> Builtin {
+        self.priority = priority;
+        self
+    }
+
+    pub fn root_only(mut self) -> Builtin {
+        self.role = UserRole::Admin;
+        self
+    }
+}
+
+impl Command for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn aliases(&self) -> &[&str] {
+        self.aliases
+    }
+
+    fn priority(&self) -> CommandPriority {
+        self.priority.clone()
+    }
+
+    fn role(&self) -> UserRole {
+        self.role.clone()
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn spec(&self) -> Spec {
+        self.spec.clone()
+    }
+
+    fn execute(&self, args: &Args, session_context: &mut SessionContext) -> TiksResult {
+        (self.run)(args, session_context)
+    }
+}
+
+
+// check the words against the spec, then run
+pub fn execute(command: &dyn Command, words: &[String], session_context: &mut SessionContext) -> TiksResult {
+    let spec = command.spec();
+    match spec.parse(command.name(), words) {
+        Ok(args) if args.help => Ok((STATUE_CODE,spec.help(command.name(), command.help()))),
+        Ok(args) => command.execute(&args, session_context),
+        Err(usage) => Err(usage),
+    }
+}
+
+
+lazy_static!{
+    static ref REGISTRY: RwLock<Vec<Arc<dyn Command>>> = RwLock::new(builtins());
+}
+
+// add a command, replaces the one with the same name;
+// its role goes to root.commands, which the permission check reads
+pub fn register(command: Arc<dyn Command>) {
+    let mut names = vec![command.name().to_string()];
+    names.extend(command.aliases().iter().map(|a| a.to_string()));
+    let root = command.role() == UserRole::Admin;
+    {
+        let mut registry = REGISTRY.write().unwrap();
+        registry.retain(|c| c.name() != command.name());
+        registry.push(command);
+    }
+    // after the lock: the settings read the registry for their defaults
+    config::set_root_only(&names, root);
+}
+
+// by name or alias
+pub fn find(name: &str) -> Option<Arc<dyn Command>> {
+    REGISTRY.read().unwrap()
+        .iter()
+        .find(|c| c.name() == name || c.aliases().contains(&name))
+        .cloned()
+}
+
+// in order of registration
+pub fn all() -> Vec<Arc<dyn Command>> {
+    REGISTRY.read().unwrap().clone()
+}
+
+// names and aliases
+pub fn names() -> Vec<String> {
+    let mut output = Vec::new();
+    for command in all() {
+        output.push(command.name().to_string());
+        output.extend(command.aliases().iter().map(|a| a.to_string()));
+    }
+    output
+}
+
+// names and aliases that need root
+pub fn root_names() -> Vec<String> {
+    let mut output = Vec::new();
+    for command in all().iter().filter(|c| c.role() == UserRole::Admin) {
+        output.push(command.name().to_string());
+        output.extend(command.aliases().iter().map(|a| a.to_string()));
+    }
+    output
+}
@@ -0,0 +1,280 @@
+// what a command accepts: flags and positional arguments
+// one parser for every builtin, usage errors and `--help` come from the spec
+//
+//  -la          short flags, combined
+//  --all        long flag, also `-all` as Tiks always accepted
+//  -f VALUE     flag with a value, also `-fVALUE`, `--fix=VALUE`
+//  --           the rest are arguments
+//  --help       help of the command
+//
+// flags are only read before the first argument, so `sudo ls -l` keeps `-l`
+// an unknown -x is an error; echo, test, expr and the like use pass_through()
+// and take every word as an argument
+use crate::start::error::TiksError;
+use crate::start::state_code::usage_error;
+
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ArgType {
+    Text,
+    Int,
+}
+
+#[derive(Clone,Debug)]
+pub struct Flag {
+    pub short: char,
+    pub long: &'static str,  // "" when there is none
+    pub value: Option<&'static str>,  // name of the value, None for a switch
+    pub help: &'static str,
+}
+
+#[derive(Clone,Debug)]
+pub struct Positional {
+    pub name: &'static str,
+    pub kind: ArgType,
+    pub required: bool,
+    pub many: bool,
+}
+
+#[derive(Clone,Debug,Default)]
+pub struct Spec {
+    pub flags: Vec<Flag>,
+    pub args: Vec<Positional>,
+    pub pass_through: bool,
+}
+
+// parsed words of a command
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Args {
+    pub flags: Vec<(char,&'static str,Option<String>)>,
+    pub values: Vec<String>,
+    pub help: bool,
+}
+
+impl Args {
+    // by short or long name: has("a"), has("all")
+    pub fn has(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.find(name).and_then(|f| f.2.as_deref())
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.values.get(index).map(|v| v.as_str())
+    }
+
+    // checked by the spec already
+    pub fn int(&self, index: usize) -> Option<i64> {
+        self.get(index).and_then(|v| v.parse().ok())
+    }
+
+    fn find(&self, name: &str) -> Option<&(char,&'static str,Option<String>)> {
+        self.flags.iter().find(|(short,long,_)| *long == name || short.to_string() == name)
+    }
+}
+
+
+impl Spec {
+    pub fn new() -> Spec {
+        Spec::default()
+    }
+
+    // -s, --long
+    pub fn flag(mut self, short: char, long: &'static str, help: &'static str) -> Spec {
+        self.flags.push(Flag { short, long, value: None, help });
+        self
+    }
+
+    // -s VALUE, --long VALUE
+    pub fn option(mut self, short: char, long: &'static str, value: &'static str, help: &'static str) -> Spec {
+        self.flags.push(Flag { short, long, value: Some(value), help });
+        self
+    }
+
+    // no flags, `-x` is an argument: `echo -n`, `test -f a`, `expr 1 - -2`
+    pub fn pass_through(mut self) -> Spec {
+        self.pass_through = true;
+        self
+    }
+
+    pub fn arg(self, name: &'static str) -> Spec {
+        self.positional(name, ArgType::Text, true, false)
+    }
+
+    pub fn int(self, name: &'static str) -> Spec {
+        self.positional(name, ArgType::Int, true, false)
+    }
+
+    pub fn optional(self, name: &'static str) -> Spec {
+        self.positional(name, ArgType::Text, false, false)
+    }
+
+    pub fn optional_int(self, name: &'static str) -> Spec {
+        self.positional(name, ArgType::Int, false, false)
+    }
+
+    // one or more
+    pub fn many(self, name: &'static str) -> Spec {
+        self.positional(name, ArgType::Text, true, true)
+    }
+
+    // zero or more
+    pub fn rest(self, name: &'static str) -> Spec {
+        self.positional(name, ArgType::Text, false, true)
+    }
+
+    fn positional(mut self, name: &'static str, kind: ArgType, required: bool, many: bool) -> Spec {
+        self.args.push(Positional { name, kind, required, many });
+        self
+    }
+
+    pub fn parse(&self, command: &str, words: &[String]) -> Result<Args,TiksError> {
+        let mut args = Args::default();
+        let mut words = words.iter();
+        let mut flags_done = self.pass_through;
+
+        while let Some(word) = words.next() {
+            // `echo --help` and `[ "$1" = --help ]` keep it as a word
+            if word == "--help" && args.values.is_empty() && !self.pass_through {
+                args.help = true;
+                return Ok(args);
+            }
+            if flags_done || !word.starts_with('-') || word == "-" || is_number(word) {
+                flags_done = true;
+                args.values.push(word.clone());
+                continue;
+            }
+            if word == "--" {
+                flags_done = true;
+                continue;
+            }
+
+            // --long, --long=value, -long
+            let long = word.trim_start_matches('-');
+            let (long, inline) = match long.split_once('=') {
+                Some((l, v)) if word.starts_with("--") => (l, Some(v.to_string())),
+                _ => (long, None),
+            };
+            if let Some(flag) = self.flags.iter().find(|f| !f.long.is_empty() && f.long == long) {
+                let value = match (flag.value, inline) {
+                    (None, None) => None,
+                    (None, Some(_)) => return Err(self.error(command, &format!("option --{} takes no value", long))),
+                    (Some(_), Some(v)) => Some(v),
+                    (Some(name), None) => match words.next() {
+                        Some(v) => Some(v.clone()),
+                        None => return Err(self.error(command, &format!("option --{} needs {}", long, name))),
+                    },
+                };
+                args.flags.push((flag.short, flag.long, value));
+                continue;
+            }
+            if word.starts_with("--") {
+                return Err(self.error(command, &format!("unknown option {}", word)));
+            }
+
+            // -abc, -fVALUE
+            let cluster = &word[1..];
+            for (i, c) in cluster.char_indices() {
+                let flag = match self.flags.iter().find(|f| f.short == c) {
+                    Some(f) => f,
+                    None => return Err(self.error(command, &format!("unknown option -{}", c))),
+                };
+                let Some(name) = flag.value else {
+                    args.flags.push((flag.short, flag.long, None));
+                    continue;
+                };
+                // the rest of the word or the next word is the value
+                let rest = &cluster[i + c.len_utf8()..];
+                let value = match rest.is_empty() {
+                    false => rest.to_string(),
+                    true => match words.next() {
+                        Some(v) => v.clone(),
+                        None => return Err(self.error(command, &format!("option -{} needs {}", c, name))),
+                    },
+                };
+                args.flags.push((flag.short, flag.long, Some(value)));
+                break;
+            }
+        }
+
+        self.check(command, &args)?;
+        Ok(args)
+    }
+
+    // arity and types
+    fn check(&self, command: &str, args: &Args) -> Result<(),TiksError> {
+        let mut values = args.values.iter();
+        for positional in &self.args {
+            let taken: Vec<&String> = match positional.many {
+                true => values.by_ref().collect(),
+                false => values.next().into_iter().collect(),
+            };
+            if taken.is_empty() && positional.required {
+                return Err(self.error(command, &format!("missing {}", positional.name)));
+            }
+            if positional.kind == ArgType::Int {
+                if let Some(bad) = taken.iter().find(|v| v.parse::<i64>().is_err()) {
+                    return Err(self.error(command, &format!("{} must be a number, not `{}`", positional.name, bad)));
+                }
+            }
+        }
+        if let Some(extra) = values.next() {
+            return Err(self.error(command, &format!("unexpected argument `{}`", extra)));
+        }
+        Ok(())
+    }
+
+    fn error(&self, command: &str, msg: &str) -> TiksError {
+        usage_error(&format!("{}: {}\n{}", command, msg, self.usage(command)))
+    }
+
+    // Usage: tar [-zxvf] FILE [TO]
+    pub fn usage(&self, command: &str) -> String {
+        let mut usage = vec![command.to_string()];
+        let switches: String = self.flags.iter()
+            .filter(|f| f.value.is_none())
+            .map(|f| f.short)
+            .collect();
+        if !switches.is_empty() {
+            usage.push(format!("[-{}]", switches));
+        }
+        for flag in self.flags.iter().filter(|f| f.value.is_some()) {
+            usage.push(format!("[-{} {}]", flag.short, flag.value.unwrap_or_default()));
+        }
+        for positional in &self.args {
+            usage.push(match (positional.required, positional.many) {
+                (true, false) => positional.name.to_string(),
+                (false, false) => format!("[{}]", positional.name),
+                (true, true) => format!("{}...", positional.name),
+                (false, true) => format!("[{}...]", positional.name),
+            });
+        }
+        format!("Usage: {}", usage.join(" "))
+    }
+
+    // text of `cmd --help`
+    pub fn help(&self, command: &str, about: &str) -> String {
+        let mut output = format!("{}: {}\n{}", command, about, self.usage(command));
+        if !self.flags.is_empty() {
+            output.push_str("\nOptions:");
+            for flag in &self.flags {
+                let mut name = format!("-{}", flag.short);
+                if !flag.long.is_empty() {
+                    name.push_str(&format!(", --{}", flag.long));
+                }
+                if let Some(value) = flag.value {
+                    name.push_str(&format!(" {}", value));
+                }
+                output.push_str(&format!("\n    {:<24}{}", name, flag.help));
+            }
+        }
+        output
+    }
+}
+
+// `exit -1` is an argument
+fn is_number(word: &str) -> bool {
+    word.len() > 1 && word[1..].chars().all(|c| c.is_ascii_digit())
+}
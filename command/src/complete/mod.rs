@@ -0,0 +1,83 @@
+// tab completion at the prompt
+// the first word of a command: functions, aliases, builtins and programs in PATH
+// the other words: file names
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+use crate::commands::registry;
+use crate::env::path_programs;
+
+
+pub struct TiksHelper {
+    pub functions: Vec<String>,  // names of the session, updated before each line
+    pub aliases: Vec<String>,
+    files: FilenameCompleter,
+}
+
+impl TiksHelper {
+    pub fn new() -> TiksHelper {
+        TiksHelper {
+            functions: Vec::new(),
+            aliases: Vec::new(),
+            files: FilenameCompleter::new(),
+        }
+    }
+
+    // names a command may start with, sorted
+    pub fn commands(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.iter()
+            .chain(&self.aliases)
+            .cloned()
+            .chain(registry::names())
+            .filter(|n| n.starts_with(prefix))
+            .collect();
+        // every program in PATH is too many for an empty word
+        if !prefix.is_empty() {
+            names.extend(path_programs(prefix));
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+impl Default for TiksHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Completer for TiksHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .rfind(|c: char| c.is_whitespace() || matches!(c, ';' | '|' | '&' | '(' | '{'))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let before = line[..start].trim_end();
+        let first_word = before.is_empty() || before.ends_with([';', '|', '&', '(', '{']);
+        let word = &line[start..pos];
+        if !first_word || word.contains('/') {
+            return self.files.complete(line, pos, ctx);
+        }
+        let pairs = self.commands(word)
+            .into_iter()
+            .map(|name| Pair { display: name.clone(), replacement: name })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for TiksHelper {
+    type Hint = String;
+}
+
+impl Highlighter for TiksHelper {}
+
+impl Validator for TiksHelper {}
+
+impl Helper for TiksHelper {}
@@ -0,0 +1,333 @@
+// ~/.Tiks/config.toml: settings that used to be fixed in the code
+//
+//   [run]     semaphore = 2                   `&` commands running at the same time
+//   [suggest] threshold = 1                   edit distance of "Did you mean?"
+//   [log]     error_log = "~/.Tiks/error.log"
+//   [prompt]  user = "32;1", path = "34"      ANSI colors of the prompt
+//   [root]    commands = ["rm", ...]          commands that need `sudo`
+//   [apt.packages.tree]  version = "1.85.0", url = "http://..."
+//
+// a missing file or key keeps the default, an unknown key or a wrong value is an error;
+// `config get KEY`, `config set KEY VALUE` and `config list` use the dotted keys
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::commands::registry;
+use crate::root::SessionContext;
+use crate::start::error::{TiksError, TiksResult};
+use crate::start::state_code::{permission_denied, usage_error, STATUE_CODE};
+
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub run: Run,
+    pub suggest: Suggest,
+    pub log: Log,
+    pub prompt: Prompt,
+    pub root: RootCommands,
+    pub apt: Apt,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Run {
+    pub semaphore: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Suggest {
+    pub threshold: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Log {
+    pub error_log: String,
+}
+
+// SGR codes, `32;1` is bold green
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Prompt {
+    pub user: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RootCommands {
+    pub commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Apt {
+    pub packages: BTreeMap<String, AptPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AptPackage {
+    pub version: String,
+    pub url: String,
+}
+
+impl Default for Run {
+    fn default() -> Self {
+        Run { semaphore: 2 }
+    }
+}
+
+impl Default for Suggest {
+    fn default() -> Self {
+        Suggest { threshold: 1 }
+    }
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Log { error_log: "~/.Tiks/error.log".to_string() }
+    }
+}
+
+impl Default for Prompt {
+    fn default() -> Self {
+        Prompt { user: "32;1".to_string(), path: "34".to_string() }
+    }
+}
+
+impl Default for RootCommands {
+    fn default() -> Self {
+        // Builtin::root_only() in commands/builtins.rs
+        RootCommands { commands: registry::root_names() }
+    }
+}
+
+impl Default for Apt {
+    fn default() -> Self {
+        let tree = AptPackage {
+            version: "1.85.0".to_string(),
+            url: "http://mama.indstate.edu/users/ice/tree/src/tree-1.8.0.tgz".to_string(),
+        };
+        Apt { packages: BTreeMap::from([("tree".to_string(), tree)]) }
+    }
+}
+
+impl Config {
+    // what the types alone do not say
+    pub fn check(&self) -> Result<(), String> {
+        if self.run.semaphore == 0 {
+            return Err("run.semaphore: must be at least 1".to_string());
+        }
+        if self.log.error_log.trim().is_empty() {
+            return Err("log.error_log: must not be empty".to_string());
+        }
+        for (key, color) in [("prompt.user", &self.prompt.user), ("prompt.path", &self.prompt.path)] {
+            if color.is_empty() || !color.chars().all(|c| c.is_ascii_digit() || c == ';') {
+                return Err(format!("{}: `{}` is not a color, use numbers like \"32;1\"", key, color));
+            }
+        }
+        let names = registry::names();
+        if let Some(name) = self.root.commands.iter().find(|c| !names.contains(c)) {
+            return Err(format!("root.commands: `{}` is not a Tiks command", name));
+        }
+        for (name, package) in &self.apt.packages {
+            if !package.url.starts_with("http://") && !package.url.starts_with("https://") {
+                return Err(format!("apt.packages.{}.url: `{}` is not an http(s) link", name, package.url));
+            }
+        }
+        Ok(())
+    }
+
+    // the error log with ~ as the home directory
+    pub fn error_log(&self) -> PathBuf {
+        match (self.log.error_log.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(&self.log.error_log),
+        }
+    }
+}
+
+lazy_static! {
+    static ref CONFIG: Mutex<Config> = Mutex::new(Config::default());
+}
+
+// the settings in use, the defaults until load()
+pub fn current() -> Config {
+    CONFIG.lock().unwrap().clone()
+}
+
+fn replace(config: Config) {
+    *CONFIG.lock().unwrap() = config;
+}
+
+// the one list of root-only commands: read by the permission check and by `type`
+pub fn root_only(name: &str) -> bool {
+    CONFIG.lock().unwrap().root.commands.iter().any(|c| c == name)
+}
+
+// a command registered later brings its role: names of a root command join
+// root.commands, the others leave it
+pub fn set_root_only(names: &[String], root: bool) {
+    let commands = &mut CONFIG.lock().unwrap().root.commands;
+    commands.retain(|c| !names.contains(c));
+    if root {
+        commands.extend(names.iter().cloned());
+    }
+}
+
+pub fn config_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".Tiks").join("config.toml"))
+}
+
+// read ~/.Tiks/config.toml at startup; on an error the defaults stay
+pub fn load() -> Result<(), TiksError> {
+    let Some(file) = config_file().filter(|f| f.is_file()) else {
+        return Ok(());
+    };
+    replace(load_from(&file)?);
+    Ok(())
+}
+
+pub fn load_from(file: &Path) -> Result<Config, TiksError> {
+    let text = fs::read_to_string(file).map_err(|e| TiksError::io(format!("config: {}", file.display())).with_source(e))?;
+    let config: Config = toml::from_str(&text)
+        .map_err(|e| usage_error(&format!("config: {}: {}", file.display(), e.to_string().trim_end())))?;
+    config.check().map_err(|e| usage_error(&format!("config: {}: {}", file.display(), e)))?;
+    Ok(config)
+}
+
+// only KEY is written, the rest of the file stays as it is and missing keys stay defaults
+pub fn save_key(file: &Path, key: &str, value: Value) -> Result<(), TiksError> {
+    let mut table = match fs::read_to_string(file) {
+        Ok(text) => toml::from_str::<Table>(&text)
+            .map_err(|e| usage_error(&format!("config: {}: {}", file.display(), e.to_string().trim_end())))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Table::new(),
+        Err(e) => return Err(TiksError::io(format!("config: {}", file.display())).with_source(e)),
+    };
+    insert(&mut table, key, value)?;
+    let text = toml::to_string_pretty(&table).map_err(|e| TiksError::io("config: can't write the settings").with_source(e))?;
+    fs::write(file, text).map_err(|e| TiksError::io(format!("config: {}", file.display())).with_source(e))
+}
+
+// config list, config get KEY, config set KEY VALUE
+pub fn config(words: &[String], session_context: &mut SessionContext) -> TiksResult {
+    let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+    match words.as_slice() {
+        ["list"] | [] => Ok((STATUE_CODE, list(&current()).join("\n"))),
+        ["get", key] => Ok((STATUE_CODE, show(&get(&current(), key)?))),
+        ["set", key, value] => {
+            // the file is read by every session and holds root.commands
+            if !session_context.user_state.root.check_permission() {
+                return Err(permission_denied());
+            }
+            let config = set(&current(), key, value)?;
+            let file = config_file().ok_or_else(|| TiksError::not_found("config: no home directory"))?;
+            // packages in the file replace the default ones, so the list is written whole
+            let key = if key.starts_with("apt.packages.") { "apt.packages" } else { key };
+            save_key(&file, key, get(&config, key)?)?;
+            replace(config);
+            Ok((STATUE_CODE, String::new()))
+        }
+        _ => Err(usage_error("config: usage: config list | config get KEY | config set KEY VALUE")),
+    }
+}
+
+fn to_table(config: &Config) -> Table {
+    Table::try_from(config).unwrap_or_default()
+}
+
+// run.semaphore = 2, one line for each key
+pub fn list(config: &Config) -> Vec<String> {
+    let mut output = Vec::new();
+    flatten("", &Value::Table(to_table(config)), &mut output);
+    output
+}
+
+fn flatten(prefix: &str, value: &Value, output: &mut Vec<String>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&key, value, output);
+            }
+        }
+        _ => output.push(format!("{} = {}", prefix, value)),
+    }
+}
+
+// text as it is, anything else as toml
+fn show(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Table(_) => {
+            let mut output = Vec::new();
+            flatten("", value, &mut output);
+            output.join("\n")
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn unknown(key: &str) -> TiksError {
+    usage_error(&format!("config: unknown key `{}`, see `config list`", key))
+}
+
+pub fn get(config: &Config, key: &str) -> Result<Value, TiksError> {
+    let mut value = Value::Table(to_table(config));
+    for part in key.split('.') {
+        value = match value {
+            Value::Table(mut table) => table.remove(part).ok_or_else(|| unknown(key))?,
+            _ => return Err(unknown(key)),
+        };
+    }
+    Ok(value)
+}
+
+// VALUE is toml: 4, "32;1", ["rm", "mv"], { version = "1", url = "..." };
+// a plain word is taken as text, so `config set prompt.user 31` works too
+pub fn set(config: &Config, key: &str, value: &str) -> Result<Config, TiksError> {
+    let old = get(config, key).or_else(|e| match key.strip_prefix("apt.packages.") {
+        // a new package
+        Some(name) if !name.is_empty() && !name.contains('.') => Ok(Value::Table(Table::new())),
+        _ => Err(e),
+    })?;
+    let value = toml::from_str::<Table>(&format!("value = {}", value)).ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()));
+    // numbers and strings are both fine for a color
+    let value = match (&old, value) {
+        (Value::String(_), Value::Integer(n)) => Value::String(n.to_string()),
+        (_, value) => value,
+    };
+
+    let mut table = to_table(config);
+    insert(&mut table, key, value)?;
+    let config: Config = Value::Table(table).try_into()
+        .map_err(|e: toml::de::Error| usage_error(&format!("config: {}: {}", key, e.message())))?;
+    config.check().map_err(|e| usage_error(&format!("config: {}", e)))?;
+    Ok(config)
+}
+
+// a.b.c = value, the tables on the way are made when missing
+fn insert(table: &mut Table, key: &str, value: Value) -> Result<(), TiksError> {
+    let parts: Vec<&str> = key.split('.').collect();
+    let mut node = table;
+    for part in &parts[..parts.len() - 1] {
+        node = match node.entry(part.to_string()).or_insert_with(|| Value::Table(Table::new())) {
+            Value::Table(next) => next,
+            _ => return Err(unknown(key)),
+        };
+    }
+    node.insert(parts[parts.len() - 1].to_string(), value);
+    Ok(())
+}
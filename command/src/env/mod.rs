@@ -1,3 +1,11 @@
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
 #[cfg(target_os="linux")]
 fn set_env() -> (usize,String){
     use std::{env, path::PathBuf};
@@ -21,6 +29,7 @@ fn set_env() -> (usize,String){
     let new_path = env::join_paths(path).expect("Failed join paths");
 
     env::set_var("PATH", new_path);
+    clear_path_cache();
 
     env()
 }
@@ -42,10 +51,105 @@ fn set_env() -> (usize,String){
 
     let new_path = env::join_paths(paths).expect("Failed to join paths");
     env::set_var("PATH", new_path);
+    clear_path_cache();
 
     env()
 }
 
+// programs already found in PATH, like `hash` in bash
+// cleared when PATH changes
+lazy_static!{
+    static ref PATH_CACHE: Mutex<HashMap<String,PathBuf>> = Mutex::new(HashMap::new());
+}
+
+// look a program up in PATH, a name with `/` is taken as a path
+pub fn find_in_path(name: &str) -> Option<PathBuf>{
+    if name.contains('/'){
+        return executable(PathBuf::from(name));
+    }
+    let mut cache = PATH_CACHE.lock().unwrap();
+    // the program may be gone since
+    if let Some(path) = cache.get(name).and_then(|p| executable(p.clone())){
+        return Some(path);
+    }
+    let path = search_path(name, false).pop()?;
+    cache.insert(name.to_string(), path.clone());
+    Some(path)
+}
+
+// every match in PATH order, only the first one unless `all`
+pub fn search_path(name: &str, all: bool) -> Vec<PathBuf>{
+    search_path_in(&env::var_os("PATH").unwrap_or_default(), name, all)
+}
+
+// the same with another PATH, not cached
+pub fn search_path_in(path: &OsStr, name: &str, all: bool) -> Vec<PathBuf>{
+    let mut output = Vec::new();
+    if name.is_empty() || name.contains('/'){
+        return output;
+    }
+    for dir in env::split_paths(path){
+        if let Some(program) = executable(dir.join(name)){
+            output.push(program);
+            if !all{
+                break;
+            }
+        }
+    }
+    output
+}
+
+// names of the programs in PATH starting with prefix, for completion
+pub fn path_programs(prefix: &str) -> Vec<String>{
+    let mut output = Vec::new();
+    let path = env::var_os("PATH").unwrap_or_default();
+    for dir in env::split_paths(&path){
+        let Ok(entries) = dir.read_dir() else { continue };
+        for entry in entries.flatten(){
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(prefix) && executable(entry.path()).is_some(){
+                output.push(name);
+            }
+        }
+    }
+    output
+}
+
+// hashed path of a name
+pub fn hashed(name: &str) -> Option<PathBuf>{
+    PATH_CACHE.lock().unwrap().get(name).cloned()
+}
+
+// every hashed name, sorted
+pub fn hashed_all() -> Vec<(String,PathBuf)>{
+    let mut output: Vec<_> = PATH_CACHE.lock().unwrap()
+        .iter()
+        .map(|(k,v)| (k.clone(),v.clone()))
+        .collect();
+    output.sort();
+    output
+}
+
+pub fn clear_path_cache(){
+    PATH_CACHE.lock().unwrap().clear();
+}
+
+#[cfg(not(windows))]
+fn executable(path: PathBuf) -> Option<PathBuf>{
+    use std::os::unix::fs::PermissionsExt;
+    let metadata = path.metadata().ok()?;
+    (metadata.is_file() && metadata.permissions().mode() & 0o111 != 0).then_some(path)
+}
+
+#[cfg(windows)]
+fn executable(path: PathBuf) -> Option<PathBuf>{
+    if path.is_file(){
+        return Some(path);
+    }
+    let exe = path.with_extension("exe");
+    exe.is_file().then_some(exe)
+}
+
 pub fn init_env(){
     #[cfg(target_os="windows")]
     set_env();
@@ -0,0 +1,322 @@
+// Integer arithmetic for $((...)) and let, like C on i64:
+//
+//   = += -= *= /= %= <<= >>= &= ^= |=     right to left, update the variable
+//   ?:  ||  &&  |  ^  &  == !=  < <= > >=  << >>  + -  * / %  **
+//   - + ! ~  ++x --x  x++ x--  ( )
+//
+// a variable holds a number or another expression, unset or empty is 0
+use crate::root::SessionContext;
+use crate::start::error::TiksError;
+
+
+// how deep a variable may point to another expression
+const MAX_DEPTH: usize = 32;
+
+const OPS: [&str; 39] = [
+    "<<=", ">>=", "**", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "^=", "|=",
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "+", "-", "*", "/", "%", "<", ">", "&", "^", "|", "!", "~", "=", "?", ":", "(", ")", ",",
+];
+
+// lowest first, ** and the unary operators are above these
+const LEVELS: [&[&str]; 10] = [
+    &["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!="], &["<", "<=", ">", ">="],
+    &["<<", ">>"], &["+", "-"], &["*", "/", "%"],
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(i64),
+    Name(String),
+    Op(&'static str),
+}
+
+pub fn eval(text: &str, session_context: &mut SessionContext) -> Result<i64,TiksError>{
+    eval_depth(text, session_context, 0).map_err(|e| TiksError::usage(format!("{}: {}", text.trim(), e)))
+}
+
+fn eval_depth(text: &str, session_context: &mut SessionContext, depth: usize) -> Result<i64,String>{
+    if depth > MAX_DEPTH{
+        return Err("expression recursion level exceeded".to_string());
+    }
+    let tokens = tokenize(text)?;
+    if tokens.is_empty(){
+        return Ok(0);
+    }
+    let mut arith = Arith { tokens, pos: 0, session_context, depth };
+    let value = arith.comma(true)?;
+    match arith.tokens.get(arith.pos){
+        Some(t) => Err(format!("syntax error in expression (error token is \"{}\")", t.text())),
+        None => Ok(value),
+    }
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Num(n) => n.to_string(),
+            Token::Name(name) => name.clone(),
+            Token::Op(op) => op.to_string(),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>,String>{
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(c) = rest.chars().next(){
+        let len = if c.is_ascii_digit() {
+            let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '#' | '@' | '_'))).unwrap_or(rest.len());
+            tokens.push(Token::Num(number(&rest[..len])?));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..len].to_string()));
+            len
+        } else {
+            match OPS.iter().find(|op| rest.starts_with(**op)) {
+                Some(op) => {
+                    tokens.push(Token::Op(op));
+                    op.len()
+                }
+                None => return Err(format!("syntax error: invalid arithmetic operator (error token is \"{}\")", rest)),
+            }
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+// 42, 0x2a, 052, 16#2a
+fn number(text: &str) -> Result<i64,String>{
+    let (base, digits) = if let Some((base, digits)) = text.split_once('#') {
+        match base.parse::<u32>() {
+            Ok(base) if (2..=64).contains(&base) => (base, digits),
+            _ => return Err(format!("invalid arithmetic base (error token is \"{}\")", text)),
+        }
+    } else if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        (16, hex)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (8, &text[1..])
+    } else {
+        (10, text)
+    };
+    let mut value: i64 = 0;
+    for c in digits.chars(){
+        // digits, then a-z, A-Z, @ and _ like bash
+        let digit = match c {
+            '0'..='9' => c as u32 - '0' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 10,
+            'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+            'A'..='Z' => c as u32 - 'A' as u32 + 36,
+            '@' => 62,
+            '_' => 63,
+            _ => base,
+        };
+        if digit >= base {
+            return Err(format!("value too great for base (error token is \"{}\")", text));
+        }
+        value = value.wrapping_mul(base as i64).wrapping_add(digit as i64);
+    }
+    if digits.is_empty() {
+        return Err(format!("invalid number (error token is \"{}\")", text));
+    }
+    Ok(value)
+}
+
+struct Arith<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    session_context: &'a mut SessionContext,
+    depth: usize,
+}
+
+// `live` is false on the side of && || ?: that is not taken:
+// it is parsed, but nothing is assigned and nothing fails
+impl Arith<'_> {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if self.peek_op() == Some(op) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn comma(&mut self, live: bool) -> Result<i64,String> {
+        let mut value = self.assign(live)?;
+        while self.eat(",") {
+            value = self.assign(live)?;
+        }
+        Ok(value)
+    }
+
+    fn assign(&mut self, live: bool) -> Result<i64,String> {
+        let (Some(Token::Name(name)), Some(Token::Op(op))) = (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) else {
+            return self.ternary(live);
+        };
+        let Some(binary) = op.strip_suffix('=').filter(|b| matches!(*b, "" | "+" | "-" | "*" | "/" | "%" | "<<" | ">>" | "&" | "^" | "|")) else {
+            return self.ternary(live);
+        };
+        let name = name.clone();
+        self.pos += 2;
+        let rhs = self.assign(live)?;
+        let value = match binary {
+            "" => rhs,
+            _ => apply(binary, self.var(&name, live)?, rhs, live)?,
+        };
+        self.set(&name, value, live);
+        Ok(value)
+    }
+
+    fn ternary(&mut self, live: bool) -> Result<i64,String> {
+        let condition = self.binary(0, live)?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+        let yes = self.comma(live && condition != 0)?;
+        if !self.eat(":") {
+            return Err("`:' expected for conditional expression".to_string());
+        }
+        let no = self.assign(live && condition == 0)?;
+        Ok(if condition != 0 { yes } else { no })
+    }
+
+    fn binary(&mut self, level: usize, live: bool) -> Result<i64,String> {
+        if level == LEVELS.len() {
+            return self.power(live);
+        }
+        let mut lhs = self.binary(level + 1, live)?;
+        while let Some(op) = self.peek_op().filter(|op| LEVELS[level].contains(op)) {
+            self.pos += 1;
+            lhs = match op {
+                // the right side is always parsed, but only run when needed
+                "||" => {
+                    let rhs = self.binary(level + 1, live && lhs == 0)?;
+                    (lhs != 0 || rhs != 0) as i64
+                }
+                "&&" => {
+                    let rhs = self.binary(level + 1, live && lhs != 0)?;
+                    (lhs != 0 && rhs != 0) as i64
+                }
+                _ => {
+                    let rhs = self.binary(level + 1, live)?;
+                    apply(op, lhs, rhs, live)?
+                }
+            };
+        }
+        Ok(lhs)
+    }
+
+    // right to left: 2**3**2 is 2**9
+    fn power(&mut self, live: bool) -> Result<i64,String> {
+        let base = self.unary(live)?;
+        if !self.eat("**") {
+            return Ok(base);
+        }
+        let exp = self.power(live)?;
+        apply("**", base, exp, live)
+    }
+
+    fn unary(&mut self, live: bool) -> Result<i64,String> {
+        match self.peek_op() {
+            Some(op @ ("-" | "+" | "!" | "~")) => {
+                self.pos += 1;
+                let value = self.unary(live)?;
+                Ok(match op {
+                    "-" => value.wrapping_neg(),
+                    "+" => value,
+                    "!" => (value == 0) as i64,
+                    _ => !value,
+                })
+            }
+            Some(op @ ("++" | "--")) => {
+                self.pos += 1;
+                let Some(Token::Name(name)) = self.tokens.get(self.pos).cloned() else {
+                    return Err(format!("syntax error: operand expected after {}", op));
+                };
+                self.pos += 1;
+                let value = self.var(&name, live)?.wrapping_add(if op == "++" { 1 } else { -1 });
+                self.set(&name, value, live);
+                Ok(value)
+            }
+            _ => self.postfix(live),
+        }
+    }
+
+    fn postfix(&mut self, live: bool) -> Result<i64,String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Num(n)) => Ok(n),
+            Some(Token::Name(name)) => {
+                let value = self.var(&name, live)?;
+                if let Some(op @ ("++" | "--")) = self.peek_op() {
+                    self.pos += 1;
+                    self.set(&name, value.wrapping_add(if op == "++" { 1 } else { -1 }), live);
+                }
+                Ok(value)
+            }
+            Some(Token::Op("(")) => {
+                let value = self.comma(live)?;
+                if !self.eat(")") {
+                    return Err("missing `)'".to_string());
+                }
+                Ok(value)
+            }
+            Some(t) => Err(format!("syntax error: operand expected (error token is \"{}\")", t.text())),
+            None => Err("syntax error: operand expected".to_string()),
+        }
+    }
+
+    // the value of a variable is itself an expression
+    fn var(&mut self, name: &str, live: bool) -> Result<i64,String> {
+        match self.session_context.get_var(name) {
+            Some(value) => eval_depth(&value, self.session_context, self.depth + 1),
+            None if live && self.session_context.options.nounset => Err(format!("{}: unbound variable", name)),
+            None => Ok(0),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: i64, live: bool) {
+        if live {
+            self.session_context.set_var(name, &value.to_string());
+        }
+    }
+}
+
+fn apply(op: &str, a: i64, b: i64, live: bool) -> Result<i64,String>{
+    Ok(match op {
+        "+" => a.wrapping_add(b),
+        "-" => a.wrapping_sub(b),
+        "*" => a.wrapping_mul(b),
+        "/" | "%" if b == 0 => match live {
+            true => return Err("division by 0".to_string()),
+            false => 0,
+        },
+        "/" => a.wrapping_div(b),
+        "%" => a.wrapping_rem(b),
+        "**" if b < 0 => match live {
+            true => return Err("exponent less than 0".to_string()),
+            false => 0,
+        },
+        "**" => a.wrapping_pow(b.min(u32::MAX as i64) as u32),
+        "<<" => a.wrapping_shl(b as u32),
+        ">>" => a.wrapping_shr(b as u32),
+        "&" => a & b,
+        "^" => a ^ b,
+        "|" => a | b,
+        "==" => (a == b) as i64,
+        "!=" => (a != b) as i64,
+        "<" => (a < b) as i64,
+        "<=" => (a <= b) as i64,
+        ">" => (a > b) as i64,
+        _ => (a >= b) as i64,
+    })
+}
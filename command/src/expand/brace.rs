@@ -0,0 +1,136 @@
+// Brace expansion, before every other expansion of a word:
+//
+//  a{b,c}d        abd acd
+//  {1..5} {a..e}  ranges, {5..1} counts down
+//  {1..10..3}     a step
+//  {01..10}       zero padded to the widest end
+//  {a,b{1..2}}    nested
+//
+// only unquoted braces count, `{a}` and `{}` stay as they are
+use crate::parse::lexer::{Word, WordPart};
+
+
+#[derive(Clone, Copy, PartialEq)]
+enum Atom<'a> {
+    Char(char),           // unquoted text
+    Part(&'a WordPart),   // anything else, kept whole
+}
+
+// the words of word, in order; just word when it has no braces
+pub fn expand(word: &Word) -> Vec<Word> {
+    let atoms: Vec<Atom> = word.parts.iter()
+        .flat_map(|part| match part {
+            WordPart::Text(s) => s.chars().map(Atom::Char).collect::<Vec<_>>(),
+            _ => vec![Atom::Part(part)],
+        })
+        .collect();
+    if !atoms.contains(&Atom::Char('{')) {
+        return vec![word.clone()];
+    }
+    let mut words = Vec::new();
+    expand_atoms(&atoms, &mut words);
+    words.into_iter().map(|atoms| to_word(&atoms)).collect()
+}
+
+fn expand_atoms<'a>(atoms: &[Atom<'a>], output: &mut Vec<Vec<Atom<'a>>>) {
+    for open in 0..atoms.len() {
+        if atoms[open] != Atom::Char('{') {
+            continue;
+        }
+        let Some((close, alternatives)) = group(atoms, open) else {
+            continue;
+        };
+        for alternative in alternatives {
+            let mut next = atoms[..open].to_vec();
+            next.extend(alternative);
+            next.extend_from_slice(&atoms[close + 1..]);
+            expand_atoms(&next, output);
+        }
+        return;
+    }
+    output.push(atoms.to_vec());
+}
+
+// the group starting at atoms[open]: where it closes, and what it gives
+fn group<'a>(atoms: &[Atom<'a>], open: usize) -> Option<(usize, Vec<Vec<Atom<'a>>>)> {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    for (i, atom) in atoms.iter().enumerate().skip(open) {
+        match atom {
+            Atom::Char('{') => depth += 1,
+            Atom::Char(',') if depth == 1 => commas.push(i),
+            Atom::Char('}') => {
+                depth -= 1;
+                if depth > 0 {
+                    continue;
+                }
+                if !commas.is_empty() {
+                    let mut alternatives = Vec::new();
+                    let mut start = open + 1;
+                    for end in commas.into_iter().chain([i]) {
+                        alternatives.push(atoms[start..end].to_vec());
+                        start = end + 1;
+                    }
+                    return Some((i, alternatives));
+                }
+                let text: Option<String> = atoms[open + 1..i].iter()
+                    .map(|a| match a { Atom::Char(c) => Some(*c), Atom::Part(_) => None })
+                    .collect();
+                let items = sequence(&text?)?;
+                return Some((i, items.into_iter().map(|s| s.chars().map(Atom::Char).collect()).collect()));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// x..y or x..y..step, numbers or single letters
+fn sequence(text: &str) -> Option<Vec<String>> {
+    let ends: Vec<&str> = text.split("..").collect();
+    let (start, end, step) = match ends.as_slice() {
+        [start, end] => (*start, *end, 1),
+        [start, end, step] => (*start, *end, step.parse::<i64>().ok()?),
+        _ => return None,
+    };
+    let step = step.unsigned_abs().max(1) as usize;
+
+    if let (Ok(a), Ok(b)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        // 01..10: as wide as the widest end
+        let padded = |s: &str| s.trim_start_matches('-').len() > 1 && s.trim_start_matches('-').starts_with('0');
+        let width = if padded(start) || padded(end) { start.len().max(end.len()) } else { 0 };
+        let numbers: Vec<i64> = match a <= b {
+            true => (a..=b).step_by(step).collect(),
+            false => (b..=a).rev().step_by(step).collect(),
+        };
+        return Some(numbers.iter().map(|n| match n < &0 {
+            true => format!("-{:0>w$}", n.unsigned_abs(), w = width.saturating_sub(1)),
+            false => format!("{:0>w$}", n, w = width),
+        }).collect());
+    }
+
+    let (mut a, mut b) = (start.chars(), end.chars());
+    match (a.next(), a.next(), b.next(), b.next()) {
+        (Some(a), None, Some(b), None) if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => {
+            let (a, b) = (a as u8, b as u8);
+            let letters: Vec<u8> = match a <= b {
+                true => (a..=b).step_by(step).collect(),
+                false => (b..=a).rev().step_by(step).collect(),
+            };
+            Some(letters.into_iter().map(|c| (c as char).to_string()).collect())
+        }
+        _ => None,
+    }
+}
+
+fn to_word(atoms: &[Atom]) -> Word {
+    let mut word = Word::default();
+    for atom in atoms {
+        match (atom, word.parts.last_mut()) {
+            (Atom::Char(c), Some(WordPart::Text(s))) => s.push(*c),
+            (Atom::Char(c), _) => word.parts.push(WordPart::Text(c.to_string())),
+            (Atom::Part(part), _) => word.parts.push((*part).clone()),
+        }
+    }
+    word
+}
@@ -0,0 +1,121 @@
+// File name expansion: `*.log`, `src/**/*.rs`, `day[0-9]?.md`
+//
+// the pattern is matched one path component at a time, `**` alone as a component
+// is any number of directories; names starting with `.` only match a pattern
+// starting with `.`, unless dotglob
+use std::fs;
+use std::path::Path;
+
+use super::pattern;
+
+
+// any unescaped * ? [ in the pattern
+pub fn has_magic(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '*' | '?' | '[' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+// the pattern as plain text
+pub fn unescape(pattern: &str) -> String {
+    let mut output = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => output.extend(chars.next()),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+// the paths matching pattern, sorted; empty when nothing matches
+pub fn glob(pattern: &str, dotglob: bool) -> Vec<String> {
+    let absolute = pattern.starts_with('/');
+    let components: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+    // "" is the current directory, paths are kept as they are written
+    let mut paths = vec![if absolute { "/".to_string() } else { String::new() }];
+
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        let mut next = Vec::new();
+        for base in &paths {
+            if *component == "**" {
+                // zero or more directories, every file too when it ends the pattern
+                if !base.is_empty() || !last {
+                    next.push(base.clone());
+                }
+                walk(base, dotglob, last, &mut next);
+            } else if !has_magic(component) {
+                let path = join(base, &unescape(component));
+                if last || Path::new(&path).is_dir() {
+                    next.push(path);
+                }
+            } else {
+                for name in read_names(base) {
+                    if !visible(&name, component, dotglob) || !pattern::matches(component, &name) {
+                        continue;
+                    }
+                    let path = join(base, &name);
+                    if last || Path::new(&path).is_dir() {
+                        next.push(path);
+                    }
+                }
+            }
+        }
+        paths = next;
+    }
+
+    let mut paths: Vec<String> = paths.into_iter()
+        .filter(|p| !p.is_empty() && fs::symlink_metadata(p).is_ok())
+        .map(|p| if pattern.ends_with('/') && !p.ends_with('/') { p + "/" } else { p })
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn join(base: &str, name: &str) -> String {
+    match base {
+        "" => name.to_string(),
+        "/" => format!("/{}", name),
+        _ => format!("{}/{}", base, name),
+    }
+}
+
+fn read_names(base: &str) -> Vec<String> {
+    let dir = if base.is_empty() { "." } else { base };
+    match fs::read_dir(dir) {
+        Ok(entries) => entries.flatten().filter_map(|e| e.file_name().into_string().ok()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn visible(name: &str, component: &str, dotglob: bool) -> bool {
+    !name.starts_with('.') || component.starts_with('.') || component.starts_with("\\.") || dotglob
+}
+
+// everything under base for `**`, without following links to directories
+fn walk(base: &str, dotglob: bool, files: bool, output: &mut Vec<String>) {
+    for name in read_names(base) {
+        if name.starts_with('.') && !dotglob {
+            continue;
+        }
+        let path = join(base, &name);
+        let is_dir = fs::symlink_metadata(&path).map(|m| m.is_dir()).unwrap_or(false);
+        if is_dir || files {
+            output.push(path.clone());
+        }
+        if is_dir {
+            walk(&path, dotglob, files, output);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+// Word expansion, done right before a command gets its arguments
+pub mod arith;
+pub mod brace;
+pub mod glob;
+pub mod pattern;
+
+use crate::parse::lexer::{ParamOp, Word, WordPart};
+use crate::parse::parser::is_name;
+use crate::root::SessionContext;
+use crate::run::substitute;
+use crate::start::state_code::status_value;
+use crate::start::error::TiksError;
+use std::{env, fs};
+
+
+pub fn expand_word(word: &Word, session_context: &mut SessionContext) -> Result<String,TiksError>{
+    let mut output = String::new();
+    for part in &word.parts{
+        output.push_str(&expand_part(part, session_context)?);
+    }
+    Ok(output)
+}
+
+// a word can give more than one field: a{b,c} gives one for each word in braces,
+// "$@" one for every argument and none when there are no arguments,
+// an unquoted $(...) is split on blanks,
+// then every field with an unquoted * ? [ becomes the file names it matches
+pub fn expand_fields(word: &Word, session_context: &mut SessionContext) -> Result<Vec<String>,TiksError>{
+    let mut output = Vec::new();
+    for word in brace::expand(word){
+        output.extend(fields(&word, session_context)?);
+    }
+    Ok(output)
+}
+
+fn fields(word: &Word, session_context: &mut SessionContext) -> Result<Vec<String>,TiksError>{
+    // fields are built as patterns: quoted text is escaped
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut keep = false;  // current is a field even when empty
+    for part in &word.parts{
+        match part{
+            WordPart::Param { name, .. } if name == "@" => {
+                for (i, arg) in session_context.args.iter().enumerate(){
+                    if i > 0{
+                        fields.push(std::mem::take(&mut current));
+                    }
+                    current.push_str(&pattern::escape(arg));
+                    keep = true;
+                }
+            }
+            WordPart::Command { quoted: false, .. } => {
+                let output = expand_part(part, session_context)?;
+                for (i, piece) in output.split([' ', '\t', '\n']).enumerate(){
+                    // a blank ends the field so far
+                    if i > 0 && (keep || !current.is_empty()){
+                        fields.push(std::mem::take(&mut current));
+                        keep = false;
+                    }
+                    current.push_str(&piece.replace('\\', "\\\\"));
+                }
+            }
+            WordPart::Text(s) => {
+                current.push_str(s);
+                keep = true;
+            }
+            WordPart::Param { quoted: false, .. } | WordPart::Braced { quoted: false, .. } => {
+                current.push_str(&expand_part(part, session_context)?.replace('\\', "\\\\"));
+                keep = true;
+            }
+            _ => {
+                current.push_str(&pattern::escape(&expand_part(part, session_context)?));
+                keep = true;
+            }
+        }
+    }
+    if keep || !current.is_empty(){
+        fields.push(current);
+    }
+
+    let options = &session_context.options;
+    let mut output = Vec::new();
+    for field in fields{
+        if options.noglob || !glob::has_magic(&field){
+            output.push(glob::unescape(&field));
+            continue;
+        }
+        let paths = glob::glob(&field, options.dotglob);
+        match paths.is_empty(){
+            false => output.extend(paths),
+            true if options.failglob => return Err(TiksError::not_found(format!("no match: {}", glob::unescape(&field)))),
+            true if options.nullglob => {}
+            true => output.push(glob::unescape(&field)),
+        }
+    }
+    Ok(output)
+}
+
+// a word used as a pattern (`case`, ${X#pattern}): quoted parts match themselves only
+pub fn expand_pattern(word: &Word, session_context: &mut SessionContext) -> Result<String,TiksError>{
+    let mut output = String::new();
+    for part in &word.parts{
+        match part{
+            WordPart::Text(s) => output.push_str(s),
+            WordPart::Quoted(s) => output.push_str(&pattern::escape(s)),
+            WordPart::Param { quoted: false, .. } | WordPart::Braced { quoted: false, .. } | WordPart::Command { quoted: false, .. } => {
+                output.push_str(&expand_part(part, session_context)?);
+            }
+            _ => output.push_str(&pattern::escape(&expand_part(part, session_context)?)),
+        }
+    }
+    Ok(output)
+}
+
+fn expand_part(part: &WordPart, session_context: &mut SessionContext) -> Result<String,TiksError>{
+    match part{
+        WordPart::Text(s) | WordPart::Quoted(s) => Ok(s.clone()),
+        WordPart::Param { name, .. } => match param(name, session_context){
+            Some(value) => Ok(value),
+            None => unset(name, session_context),
+        },
+        WordPart::Braced { name, op, word, .. } => braced(name, *op, word, session_context),
+        WordPart::Tilde(user) => Ok(tilde(user)),
+        WordPart::Command { text, .. } => substitute(text, session_context),
+        WordPart::Arith { word, .. } => {
+            let text = expand_word(word, session_context)?;
+            Ok(arith::eval(&text, session_context)?.to_string())
+        }
+    }
+}
+
+// ${NAME<op>word}, the word is only expanded when it is used
+fn braced(name: &str, op: ParamOp, word: &Word, session_context: &mut SessionContext) -> Result<String,TiksError>{
+    let value = param(name, session_context);
+    // the `:` forms treat an empty value as unset
+    let is_set = |colon: bool| match &value{
+        Some(v) => !(colon && v.is_empty()),
+        None => false,
+    };
+    match op{
+        ParamOp::Default(colon) if !is_set(colon) => expand_word(word, session_context),
+        ParamOp::Assign(colon) if !is_set(colon) => {
+            if !is_name(name){
+                return Err(TiksError::usage(format!("${}: cannot assign in this way", name)));
+            }
+            let value = expand_word(word, session_context)?;
+            session_context.set_var(name, &value);
+            Ok(value)
+        }
+        ParamOp::Error(colon) if !is_set(colon) => {
+            let message = expand_word(word, session_context)?;
+            let message = if message.is_empty() { "parameter null or not set".to_string() } else { message };
+            Err(TiksError::usage(format!("{}: {}", name, message)))
+        }
+        ParamOp::Alternate(colon) => match is_set(colon){
+            true => expand_word(word, session_context),
+            false => Ok(String::new()),
+        },
+        ParamOp::Length => {
+            let value = match value { Some(v) => v, None => unset(name, session_context)? };
+            Ok(value.chars().count().to_string())
+        }
+        ParamOp::RemovePrefix(longest) | ParamOp::RemoveSuffix(longest) => {
+            let value = match value { Some(v) => v, None => unset(name, session_context)? };
+            let pattern = expand_pattern(word, session_context)?;
+            Ok(remove(&value, &pattern, longest, matches!(op, ParamOp::RemovePrefix(_))))
+        }
+        _ => Ok(value.unwrap_or_default()),
+    }
+}
+
+// unset is empty, unless `set -u`
+fn unset(name: &str, session_context: &SessionContext) -> Result<String,TiksError>{
+    if session_context.options.nounset{
+        return Err(TiksError::usage(format!("{}: unbound variable", name)));
+    }
+    Ok(String::new())
+}
+
+// special parameters, then variables, None when unset
+fn param(name: &str, session_context: &SessionContext) -> Option<String>{
+    let args = &session_context.args;
+    match name{
+        "?" => Some(status_value(session_context.last_status).to_string()),
+        "#" => Some(args.len().to_string()),
+        "@" | "*" => Some(args.join(" ")),
+        _ => match name.parse::<usize>(){
+            Ok(0) => Some(session_context.shell_name.clone()),
+            Ok(n) => args.get(n - 1).cloned(),
+            Err(_) => session_context.get_var(name),
+        },
+    }
+}
+
+// the shortest or longest match of pattern at the start or the end of value, cut off
+fn remove(value: &str, pattern: &str, longest: bool, prefix: bool) -> String{
+    let mut cuts: Vec<usize> = value.char_indices().map(|(i, _)| i).chain([value.len()]).collect();
+    // the cuts in the order to try them
+    if longest == prefix{
+        cuts.reverse();
+    }
+    for cut in cuts{
+        let (head, tail) = value.split_at(cut);
+        if prefix && pattern::matches(pattern, head){
+            return tail.to_string();
+        }
+        if !prefix && pattern::matches(pattern, tail){
+            return head.to_string();
+        }
+    }
+    value.to_string()
+}
+
+// ~ is $HOME, ~user the home of user in /etc/passwd, left alone when unknown
+fn tilde(user: &str) -> String{
+    let home = match user{
+        "" => env::var("HOME").ok().or_else(|| dirs::home_dir().and_then(|h| h.to_str().map(|s| s.to_string()))),
+        _ => fs::read_to_string("/etc/passwd").ok().and_then(|passwd| {
+            passwd.lines()
+            .map(|line| line.split(':').collect::<Vec<_>>())
+            .find(|fields| fields.len() > 5 && fields[0] == user)
+            .map(|fields| fields[5].to_string())
+        }),
+    };
+    home.unwrap_or_else(|| format!("~{}", user))
+}
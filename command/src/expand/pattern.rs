@@ -0,0 +1,100 @@
+// shell patterns, for `case` and file names
+//
+//  *        any text
+//  ?        one character
+//  [abc]    one of a, b, c; [a-z] a range; [!a] or [^a] anything else
+//  \*       a plain *
+
+
+// the whole text must match
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text)
+}
+
+// \ before every character with a meaning in patterns
+pub fn escape(text: &str) -> String {
+    let mut output = String::new();
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // where the last * was, and the text it has taken so far
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match class(&pattern[p..], text[t]) {
+                Some((true, len)) => Some(len),
+                Some((false, _)) => None,
+                // no closing ], a plain [
+                None => (text[t] == '[').then_some(1),
+            },
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(2),
+            Some(&c) => (c == text[t]).then_some(1),
+            None => None,
+        };
+        match (step, star) {
+            (Some(len), _) => {
+                p += len;
+                t += 1;
+            }
+            // let the last * take one more character
+            (None, Some((sp, st))) => {
+                star = Some((sp, st + 1));
+                p = sp + 1;
+                t = st + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// [...] at the start of pattern: (matched, length of the class)
+fn class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut found = false;
+    let mut first = true;
+    loop {
+        let start = *pattern.get(i)?;
+        // ] right after [ or [! is a member
+        if start == ']' && !first {
+            return Some((found != negated, i + 1));
+        }
+        first = false;
+        let start = match start {
+            '\\' => {
+                i += 1;
+                *pattern.get(i)?
+            }
+            x => x,
+        };
+        if pattern.get(i + 1) == Some(&'-') && !matches!(pattern.get(i + 2), Some(']') | None) {
+            let end = pattern[i + 2];
+            found |= start <= c && c <= end;
+            i += 3;
+        } else {
+            found |= start == c;
+            i += 1;
+        }
+    }
+}
@@ -1,17 +1,24 @@
 pub mod root;
 pub mod commands;
+pub mod parse;
+pub mod expand;
+pub mod redirect;
+pub mod complete;
 mod set;
 pub mod process;
 pub mod run;
 pub mod signal;
 mod priority;
 pub mod env;
+pub mod config;
 mod test;
 pub mod start;
 mod tauri_func;
 
 pub use crate::root::SESSION;
 pub use crate::start::*;
-pub use crate::tauri_func::run_command;
+pub use crate::tauri_func::{run_command, run_command_with};
+pub use crate::redirect::{Buffer, Output, Streams};
+pub use crate::start::error::{Category, TiksError, TiksResult};
 pub use crate::commands::command::{HISTROY,history_push,pwd,whoami,get_time};
 pub use crate::set::set::home_dir;
\ No newline at end of file
@@ -0,0 +1,147 @@
+// Syntax tree of a command line
+//
+//   list      := and_or ((';' | '&' | newline) and_or)*
+//   and_or    := pipeline (('&&' | '||') pipeline)*
+//   pipeline  := command ('|' command)*
+//   simple    := (name '=' word)* word*   redirects can come between the words
+//   command   := simple | '{' list '}' | '(' list ')' | compound   followed by redirects
+//              | name '(' ')' command | 'function' name ['(' ')'] command
+//   compound  := 'if' list 'then' list ('elif' list 'then' list)* ['else' list] 'fi'
+//              | ('while' | 'until') list 'do' list 'done'
+//              | 'for' name ['in' word*] (';' | newline) 'do' list 'done'
+//              | 'case' word 'in' (['('] word ('|' word)* ')' list [';;'])* 'esac'
+//   redirect  := [n] ('>' | '>>' | '<' | '>&' | '<<' | '<<-' | '<<<') word | ('&>' | '&>>') word
+//                the word of << and <<- is the here-document body
+use super::lexer::{Op, Word};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub fd: usize,  // 0 for <, 1 for the others unless given
+    pub op: Op,
+    pub target: Word,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SimpleCommand {
+    pub assigns: Vec<(String, Word)>,  // NAME=value before the command name
+    pub words: Vec<Word>,
+}
+
+impl SimpleCommand {
+    pub fn name(&self) -> String {
+        self.words.first().map(|w| w.text()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandKind {
+    Simple(SimpleCommand),
+    Group(List),      // { ...; }
+    Subshell(List),   // ( ... )
+    If(If),
+    While(Loop),
+    Until(Loop),
+    For(For),
+    Case(Case),
+    Function(Function),  // a definition, the call is a simple command
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub body: Box<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct If {
+    pub branches: Vec<(List, List)>,  // condition, body: if and every elif
+    pub otherwise: Option<List>,      // else
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    pub condition: List,
+    pub body: List,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct For {
+    pub name: String,
+    pub words: Option<Vec<Word>>,  // None: `for x; do`, the arguments
+    pub body: List,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseItem {
+    pub patterns: Vec<Word>,
+    pub body: List,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    pub word: Word,
+    pub items: Vec<CaseItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub kind: CommandKind,
+    pub redirects: Vec<Redirect>,
+}
+
+impl Command {
+    // first word, used for priority and permission lookups
+    pub fn name(&self) -> String {
+        match &self.kind {
+            CommandKind::Simple(simple) => simple.name(),
+            CommandKind::Group(list) | CommandKind::Subshell(list) => list.name(),
+            CommandKind::If(_) => "if".to_string(),
+            CommandKind::While(_) => "while".to_string(),
+            CommandKind::Until(_) => "until".to_string(),
+            CommandKind::For(_) => "for".to_string(),
+            CommandKind::Case(_) => "case".to_string(),
+            CommandKind::Function(f) => f.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    And,  // &&
+    Or,   // ||
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+}
+
+impl AndOr {
+    pub fn name(&self) -> String {
+        self.first.commands.first().map(|c| c.name()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListItem {
+    pub and_or: AndOr,
+    pub background: bool,  // ended by `&`
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct List {
+    pub items: Vec<ListItem>,
+}
+
+impl List {
+    pub fn name(&self) -> String {
+        self.items.first().map(|i| i.and_or.name()).unwrap_or_default()
+    }
+}
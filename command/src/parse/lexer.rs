@@ -0,0 +1,671 @@
+// Tokenizer for a command line
+// handle: 'single' "double" \escape #comment, operators and here-documents
+use std::fmt;
+
+
+// a piece of a word, quoted text is never treated as an operator
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordPart {
+    Text(String),
+    Quoted(String),
+    Param { name: String, quoted: bool },  // $?, $NAME, $1, $@, $#, ${NAME}
+    Braced { name: String, op: ParamOp, word: Word, quoted: bool },  // ${NAME:-word} ...
+    Tilde(String),  // ~ or ~user at the start of a word
+    Command { text: String, quoted: bool },  // $(text) or `text`, replaced by its stdout
+    Arith { word: Word, quoted: bool },  // $((word)), expanded then computed
+}
+
+// what ${NAME<op>word} does, the bool is the `:` form (or `##` `%%`, the longest match)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamOp {
+    Length,             // ${#NAME}
+    Default(bool),      // ${NAME-word}  ${NAME:-word}
+    Assign(bool),       // ${NAME=word}  ${NAME:=word}
+    Error(bool),        // ${NAME?word}  ${NAME:?word}
+    Alternate(bool),    // ${NAME+word}  ${NAME:+word}
+    RemovePrefix(bool), // ${NAME#pattern}  ${NAME##pattern}
+    RemoveSuffix(bool), // ${NAME%pattern}  ${NAME%%pattern}
+}
+
+impl ParamOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParamOp::Length => "",
+            ParamOp::Default(colon) => if *colon { ":-" } else { "-" },
+            ParamOp::Assign(colon) => if *colon { ":=" } else { "=" },
+            ParamOp::Error(colon) => if *colon { ":?" } else { "?" },
+            ParamOp::Alternate(colon) => if *colon { ":+" } else { "+" },
+            ParamOp::RemovePrefix(longest) => if *longest { "##" } else { "#" },
+            ParamOp::RemoveSuffix(longest) => if *longest { "%%" } else { "%" },
+        }
+    }
+}
+
+const PARAM_OPS: [(&str, ParamOp); 12] = [
+    (":-", ParamOp::Default(true)), (":=", ParamOp::Assign(true)),
+    (":?", ParamOp::Error(true)), (":+", ParamOp::Alternate(true)),
+    ("-", ParamOp::Default(false)), ("=", ParamOp::Assign(false)),
+    ("?", ParamOp::Error(false)), ("+", ParamOp::Alternate(false)),
+    ("##", ParamOp::RemovePrefix(true)), ("#", ParamOp::RemovePrefix(false)),
+    ("%%", ParamOp::RemoveSuffix(true)), ("%", ParamOp::RemoveSuffix(false)),
+];
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+impl Word {
+    pub fn literal<T: Into<String>>(text: T) -> Word {
+        Word {
+            parts: vec![WordPart::Quoted(text.into())],
+        }
+    }
+
+    // quote removal
+    pub fn text(&self) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                WordPart::Text(s) | WordPart::Quoted(s) => output.push_str(s),
+                WordPart::Param { name, .. } => {
+                    output.push('$');
+                    output.push_str(name);
+                }
+                WordPart::Braced { name, op: ParamOp::Length, .. } => output.push_str(&format!("${{#{}}}", name)),
+                WordPart::Braced { name, op, word, .. } => {
+                    output.push_str(&format!("${{{}{}{}}}", name, op.as_str(), word.text()));
+                }
+                WordPart::Tilde(user) => {
+                    output.push('~');
+                    output.push_str(user);
+                }
+                WordPart::Command { text, .. } => output.push_str(&format!("$({})", text)),
+                WordPart::Arith { word, .. } => output.push_str(&format!("$(({}))", word.text())),
+            }
+        }
+        output
+    }
+
+    // unquoted word with exactly this text, e.g. `{` `}`
+    pub fn is_reserved(&self, text: &str) -> bool {
+        matches!(self.parts.as_slice(), [WordPart::Text(s)] if s == text)
+    }
+
+    fn push(&mut self, c: char, quoted: bool) {
+        match (self.parts.last_mut(), quoted) {
+            (Some(WordPart::Text(s)), false) | (Some(WordPart::Quoted(s)), true) => s.push(c),
+            (_, false) => self.parts.push(WordPart::Text(c.to_string())),
+            (_, true) => self.parts.push(WordPart::Quoted(c.to_string())),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Pipe,    // |
+    Or,      // ||
+    Amp,     // &
+    And,     // &&
+    Semi,    // ;
+    DSemi,   // ;; ends a case item
+    Great,   // >
+    DGreat,  // >>
+    Less,    // <
+    DLess,      // <<
+    DLessDash,  // <<-
+    TLess,      // <<<
+    GreatAnd,   // >&
+    AndGreat,   // &>
+    AndDGreat,  // &>>
+    LParen,  // (
+    RParen,  // )
+    Newline,
+}
+
+impl Op {
+    pub fn is_redirect(&self) -> bool {
+        matches!(self, Op::Great | Op::DGreat | Op::Less | Op::DLess | Op::DLessDash | Op::TLess
+            | Op::GreatAnd | Op::AndGreat | Op::AndDGreat)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Op::Pipe => "|",
+            Op::Or => "||",
+            Op::Amp => "&",
+            Op::And => "&&",
+            Op::Semi => ";",
+            Op::DSemi => ";;",
+            Op::Great => ">",
+            Op::DGreat => ">>",
+            Op::Less => "<",
+            Op::DLess => "<<",
+            Op::DLessDash => "<<-",
+            Op::TLess => "<<<",
+            Op::GreatAnd => ">&",
+            Op::AndGreat => "&>",
+            Op::AndDGreat => "&>>",
+            Op::LParen => "(",
+            Op::RParen => ")",
+            Op::Newline => "newline",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Word(Word),
+    Op(Op),
+    IoNumber(usize),  // the 2 in 2>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub col: usize, // 1-based, 0 = not from source
+}
+
+impl Token {
+    pub fn is_op(&self, op: Op) -> bool {
+        self.kind == TokenKind::Op(op)
+    }
+
+    pub fn as_word(&self) -> Option<&Word> {
+        match &self.kind {
+            TokenKind::Word(w) => Some(w),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TokenKind::Word(w) => write!(f, "{}", w.text()),
+            TokenKind::Op(op) => write!(f, "{}", op.as_str()),
+            TokenKind::IoNumber(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub msg: String,
+    pub col: usize,
+    pub incomplete: bool,  // more lines may finish the command
+}
+
+impl SyntaxError {
+    pub fn new<T: Into<String>>(msg: T, col: usize) -> SyntaxError {
+        SyntaxError { msg: msg.into(), col, incomplete: false }
+    }
+
+    pub fn incomplete<T: Into<String>>(msg: T, col: usize) -> SyntaxError {
+        SyntaxError { msg: msg.into(), col, incomplete: true }
+    }
+
+    // the input line with a ^ under the bad column
+    pub fn pointer(&self, line: &str) -> String {
+        format!("{}\n{}^\n{}", line, " ".repeat(self.col.saturating_sub(1)), self)
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: syntax error: {} (column {})", self.msg, self.col)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+
+pub fn tokenize(line: &str) -> Result<Vec<Token>, SyntaxError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut word = Word::default();
+    let mut word_col = 0; // 0 = no word started
+    let mut heredocs = Vec::new(); // (index of << token, strip tabs)
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let col = i + 1;
+        match c {
+            ' ' | '\t' | '\r' => {
+                finish_word(&mut tokens, &mut word, &mut word_col);
+                i += 1;
+            }
+            '#' if word_col == 0 => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '\'' => {
+                if word_col == 0 { word_col = col; }
+                let end = match chars[i + 1..].iter().position(|&x| x == '\'') {
+                    Some(n) => i + 1 + n,
+                    None => return Err(SyntaxError::incomplete("unterminated single quote", col)),
+                };
+                // '' is still an empty word
+                word.parts.push(WordPart::Quoted(chars[i + 1..end].iter().collect()));
+                i = end + 1;
+            }
+            '"' => {
+                if word_col == 0 { word_col = col; }
+                i = lex_double(&chars, i, &mut word)?;
+            }
+            // line continuation
+            '\\' if chars.get(i + 1) == Some(&'\n') => i += 2,
+            '\\' => {
+                if word_col == 0 { word_col = col; }
+                match chars.get(i + 1) {
+                    Some(&x) => word.push(x, true),
+                    None => return Err(SyntaxError::incomplete("unexpected end of line after \\", col)),
+                }
+                i += 2;
+            }
+            '$' => {
+                if word_col == 0 { word_col = col; }
+                i = lex_dollar(&chars, i, &mut word, false)?;
+            }
+            '`' => {
+                if word_col == 0 { word_col = col; }
+                i = lex_backtick(&chars, i, &mut word, false)?;
+            }
+            // ~ and ~user, only before the first `/`
+            '~' if word_col == 0 => {
+                word_col = col;
+                let len = chars[i + 1..].iter().take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')).count();
+                match chars.get(i + 1 + len) {
+                    None | Some(' ' | '\t' | '\r' | '\n' | '/' | ':' | ';' | '&' | '|' | '<' | '>' | '(' | ')') => {
+                        word.parts.push(WordPart::Tilde(chars[i + 1..i + 1 + len].iter().collect()));
+                        i += 1 + len;
+                    }
+                    _ => {
+                        word.push('~', false);
+                        i += 1;
+                    }
+                }
+            }
+            '|' | '&' | ';' | '>' | '<' | '(' | ')' | '\n' => {
+                // 2> : a number right before a redirect is the fd
+                if matches!(c, '>' | '<') {
+                    if let [WordPart::Text(n)] = word.parts.as_slice() {
+                        if let Ok(fd) = n.parse::<usize>() {
+                            tokens.push(Token { kind: TokenKind::IoNumber(fd), col: word_col });
+                            word = Word::default();
+                            word_col = 0;
+                        }
+                    }
+                }
+                finish_word(&mut tokens, &mut word, &mut word_col);
+                let (op, len) = match (c, chars.get(i + 1), chars.get(i + 2)) {
+                    ('&', Some('&'), _) => (Op::And, 2),
+                    ('&', Some('>'), Some('>')) => (Op::AndDGreat, 3),
+                    ('&', Some('>'), _) => (Op::AndGreat, 2),
+                    ('&', _, _) => (Op::Amp, 1),
+                    ('>', Some('>'), _) => (Op::DGreat, 2),
+                    ('>', Some('&'), _) => (Op::GreatAnd, 2),
+                    ('<', Some('<'), Some('<')) => (Op::TLess, 3),
+                    ('<', Some('<'), Some('-')) => (Op::DLessDash, 3),
+                    ('<', Some('<'), _) => (Op::DLess, 2),
+                    ('<', _, _) => (Op::Less, 1),
+                    ('|', Some('|'), _) => (Op::Or, 2),
+                    ('|', _, _) => (Op::Pipe, 1),
+                    (';', Some(';'), _) => (Op::DSemi, 2),
+                    (';', _, _) => (Op::Semi, 1),
+                    ('(', _, _) => (Op::LParen, 1),
+                    (')', _, _) => (Op::RParen, 1),
+                    ('\n', _, _) => (Op::Newline, 1),
+                    _ => (Op::Great, 1),
+                };
+                if matches!(op, Op::DLess | Op::DLessDash) {
+                    heredocs.push((tokens.len(), op == Op::DLessDash));
+                }
+                tokens.push(Token { kind: TokenKind::Op(op), col });
+                i += len;
+                // bodies start on the line after the <<
+                if op == Op::Newline && !heredocs.is_empty() {
+                    i = read_heredocs(&chars, i, &mut tokens, &mut heredocs)?;
+                }
+            }
+            _ => {
+                if word_col == 0 { word_col = col; }
+                word.push(c, false);
+                i += 1;
+            }
+        }
+    }
+    finish_word(&mut tokens, &mut word, &mut word_col);
+    if let Some(&(index, _)) = heredocs.first() {
+        return Err(SyntaxError::incomplete("here-document is not closed", tokens[index].col));
+    }
+
+    Ok(tokens)
+}
+
+fn finish_word(tokens: &mut Vec<Token>, word: &mut Word, word_col: &mut usize) {
+    if *word_col != 0 {
+        tokens.push(Token {
+            kind: TokenKind::Word(std::mem::take(word)),
+            col: *word_col,
+        });
+        *word_col = 0;
+    }
+}
+
+// `"` at chars[i], returns the index after the closing one
+fn lex_double(chars: &[char], start: usize, word: &mut Word) -> Result<usize, SyntaxError> {
+    word.parts.push(WordPart::Quoted(String::new()));
+    let mut i = start + 1;
+    loop {
+        match chars.get(i) {
+            None => return Err(SyntaxError::incomplete("unterminated double quote", start + 1)),
+            Some('"') => return Ok(i + 1),
+            Some('\\') if chars.get(i + 1) == Some(&'\n') => i += 2,
+            Some('\\') if matches!(chars.get(i + 1), Some('"' | '\\' | '$' | '`')) => {
+                word.push(chars[i + 1], true);
+                i += 2;
+            }
+            Some('$') => i = lex_dollar(chars, i, word, true)?,
+            Some('`') => i = lex_backtick(chars, i, word, true)?,
+            Some(&x) => {
+                word.push(x, true);
+                i += 1;
+            }
+        }
+    }
+}
+
+// `$` at chars[i], returns the index after it
+fn lex_dollar(chars: &[char], i: usize, word: &mut Word, quoted: bool) -> Result<usize, SyntaxError> {
+    match chars.get(i + 1) {
+        Some('(') => {
+            let end = match closing_paren(chars, i + 2) {
+                Some(end) => end,
+                None => return Err(SyntaxError::incomplete("unterminated $(", i + 1)),
+            };
+            // $((...)) unless it is $( (...) ... )
+            if chars.get(i + 2) == Some(&'(') && chars[end - 1] == ')' && closing_paren(chars, i + 3) == Some(end - 1) {
+                let inner = inner_word(&chars[i + 3..end - 1], true)?;
+                word.parts.push(WordPart::Arith { word: inner, quoted });
+                return Ok(end + 1);
+            }
+            let text: String = chars[i + 2..end].iter().collect();
+            word.parts.push(substitution(text, quoted, i + 3)?);
+            Ok(end + 1)
+        }
+        Some('{') => {
+            let end = match closing_brace(chars, i + 2) {
+                Some(end) => end,
+                None => return Err(SyntaxError::incomplete("unterminated ${", i + 1)),
+            };
+            word.parts.push(braced(&chars[i + 2..end], quoted, i + 1)?);
+            Ok(end + 1)
+        }
+        Some(&c) if matches!(c, '?' | '@' | '#' | '*') || c.is_ascii_digit() => {
+            word.parts.push(WordPart::Param { name: c.to_string(), quoted });
+            Ok(i + 2)
+        }
+        Some(&c) if c.is_ascii_alphabetic() || c == '_' => {
+            let len = param_name(&chars[i + 1..]);
+            let name = chars[i + 1..i + 1 + len].iter().collect();
+            word.parts.push(WordPart::Param { name, quoted });
+            Ok(i + 1 + len)
+        }
+        _ => {
+            word.push('$', quoted);
+            Ok(i + 1)
+        }
+    }
+}
+
+// `text` at chars[i]: \` \\ \$ (and \" inside "...") lose the backslash,
+// so `a \`b\`` nests
+fn lex_backtick(chars: &[char], i: usize, word: &mut Word, quoted: bool) -> Result<usize, SyntaxError> {
+    let end = match closing_backtick(chars, i + 1) {
+        Some(end) => end,
+        None => return Err(SyntaxError::incomplete("unterminated `", i + 1)),
+    };
+    let mut text = String::new();
+    let mut j = i + 1;
+    while j < end {
+        match (chars[j], chars.get(j + 1)) {
+            ('\\', Some(&c)) if matches!(c, '`' | '\\' | '$') || (quoted && c == '"') => {
+                text.push(c);
+                j += 2;
+            }
+            (c, _) => {
+                text.push(c);
+                j += 1;
+            }
+        }
+    }
+    word.parts.push(substitution(text, quoted, i + 2)?);
+    Ok(end + 1)
+}
+
+// the commands are parsed now for the errors, and again when they run
+fn substitution(text: String, quoted: bool, col: usize) -> Result<WordPart, SyntaxError> {
+    if let Err(e) = super::parser::parse(&text) {
+        return Err(SyntaxError::new(e.msg, col + e.col.saturating_sub(1)));
+    }
+    Ok(WordPart::Command { text, quoted })
+}
+
+// index of the `)` closing a $( whose body starts at chars[start]
+fn closing_paren(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 1;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '\'' => i += chars[i + 1..].iter().position(|&x| x == '\'')? + 1,
+            '"' => i = closing_double(chars, i + 1)?,
+            '`' => i = closing_backtick(chars, i + 1)?,
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+// index of the `"` closing a "..." whose body starts at chars[start]
+fn closing_double(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '"' => return Some(i),
+            '$' if chars.get(i + 1) == Some(&'(') => i = closing_paren(chars, i + 2)?,
+            '`' => i = closing_backtick(chars, i + 1)?,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+// index of the unescaped ` after chars[start]
+fn closing_backtick(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '`' => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+// length of the parameter name at the start of chars: NAME, digits or one of ?@#*
+fn param_name(chars: &[char]) -> usize {
+    match chars.first() {
+        Some(c) if c.is_ascii_alphabetic() || *c == '_' => {
+            chars.iter().take_while(|c| c.is_ascii_alphanumeric() || **c == '_').count()
+        }
+        Some(c) if c.is_ascii_digit() => chars.iter().take_while(|c| c.is_ascii_digit()).count(),
+        Some('?' | '@' | '#' | '*') => 1,
+        _ => 0,
+    }
+}
+
+// index of the `}` closing a ${ whose body starts at chars[start]
+fn closing_brace(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 1;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '\'' | '"' => {
+                let quote = chars[i];
+                i += 1;
+                while i < chars.len() && chars[i] != quote {
+                    if quote == '"' && chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            '$' if chars.get(i + 1) == Some(&'{') => {
+                depth += 1;
+                i += 1;
+            }
+            '$' if chars.get(i + 1) == Some(&'(') => i = closing_paren(chars, i + 2)?,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+// the inside of ${...}
+fn braced(inner: &[char], quoted: bool, col: usize) -> Result<WordPart, SyntaxError> {
+    let bad = || SyntaxError::new(format!("${{{}}}: bad substitution", inner.iter().collect::<String>()), col);
+    // ${#NAME} is the length, ${#} alone is the argument count
+    if inner.len() > 1 && inner[0] == '#' && param_name(&inner[1..]) == inner.len() - 1 {
+        let name = inner[1..].iter().collect();
+        return Ok(WordPart::Braced { name, op: ParamOp::Length, word: Word::default(), quoted });
+    }
+    let len = param_name(inner);
+    if len == 0 {
+        return Err(bad());
+    }
+    let name: String = inner[..len].iter().collect();
+    let rest: String = inner[len..].iter().collect();
+    if rest.is_empty() {
+        return Ok(WordPart::Param { name, quoted });
+    }
+    let (op_text, op) = PARAM_OPS.iter().find(|(text, _)| rest.starts_with(text)).ok_or_else(bad)?;
+    let word = inner_word(&inner[len + op_text.len()..], quoted)?;
+    Ok(WordPart::Braced { name, op: *op, word, quoted })
+}
+
+// the word after the operator in ${NAME:-word}, quotes work as in a word,
+// inside "..." a ' is plain text
+fn inner_word(chars: &[char], quoted: bool) -> Result<Word, SyntaxError> {
+    let mut word = Word::default();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\'' if !quoted => {
+                let end = chars[i + 1..].iter().position(|&x| x == '\'').map(|n| i + 1 + n).unwrap_or(chars.len());
+                word.parts.push(WordPart::Quoted(chars[i + 1..end].iter().collect()));
+                i = end + 1;
+            }
+            '"' => i = lex_double(chars, i, &mut word)?,
+            '\\' if i + 1 < chars.len() => {
+                word.push(chars[i + 1], true);
+                i += 2;
+            }
+            '$' => i = lex_dollar(chars, i, &mut word, quoted)?,
+            '`' => i = lex_backtick(chars, i, &mut word, quoted)?,
+            // still a pattern for # and %, the quoted flag of the ${ covers the rest
+            x => {
+                word.push(x, false);
+                i += 1;
+            }
+        }
+    }
+    Ok(word)
+}
+
+// read the body of every pending here-document from chars[start..]
+// and put it in place of its delimiter word
+fn read_heredocs(chars: &[char], start: usize, tokens: &mut [Token], heredocs: &mut Vec<(usize, bool)>) -> Result<usize, SyntaxError> {
+    let mut i = start;
+    for (index, strip_tabs) in heredocs.drain(..) {
+        let col = tokens[index].col;
+        let delimiter = match tokens.get(index + 1).and_then(|t| t.as_word()) {
+            Some(word) => word.clone(),
+            None => return Err(SyntaxError::new("missing here-document delimiter", col)),
+        };
+        // quoted delimiter: no expansion in the body
+        let quoted = delimiter.parts.iter().any(|p| !matches!(p, WordPart::Text(_)));
+        let delimiter = delimiter.text();
+
+        let mut body = String::new();
+        loop {
+            if i >= chars.len() {
+                return Err(SyntaxError::incomplete(format!("here-document `{}` is not closed", delimiter), col));
+            }
+            let end = chars[i..].iter().position(|&x| x == '\n').map(|n| i + n);
+            let mut line: String = chars[i..end.unwrap_or(chars.len())].iter().collect();
+            i = end.map(|n| n + 1).unwrap_or(chars.len());
+            if strip_tabs {
+                line = line.trim_start_matches('\t').to_string();
+            }
+            if line == delimiter {
+                break;
+            }
+            body.push_str(&line);
+            body.push('\n');
+        }
+
+        let body = if quoted { Word::literal(body) } else { heredoc_word(&body, col)? };
+        tokens[index + 1].kind = TokenKind::Word(body);
+    }
+    Ok(i)
+}
+
+// body of an unquoted here-document: like "...", but `"` is plain text
+fn heredoc_word(body: &str, col: usize) -> Result<Word, SyntaxError> {
+    let chars: Vec<char> = body.chars().collect();
+    let mut word = Word::literal("");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&'\n') => i += 2,
+            '\\' if matches!(chars.get(i + 1), Some('\\' | '$' | '`')) => {
+                word.push(chars[i + 1], true);
+                i += 2;
+            }
+            // report errors at the `<<`
+            '$' => i = lex_dollar(&chars, i, &mut word, true).map_err(|e| SyntaxError { col, ..e })?,
+            '`' => i = lex_backtick(&chars, i, &mut word, true).map_err(|e| SyntaxError { col, ..e })?,
+            x => {
+                word.push(x, true);
+                i += 1;
+            }
+        }
+    }
+    Ok(word)
+}
@@ -0,0 +1,4 @@
+// command line -> tokens -> syntax tree
+pub mod lexer;
+pub mod ast;
+pub mod parser;
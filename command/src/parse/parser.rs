@@ -0,0 +1,453 @@
+// tokens -> syntax tree, see ast.rs for the grammar
+use super::ast::*;
+use crate::commands::alias::Aliases;
+use super::lexer::{tokenize, Op, SyntaxError, Token, TokenKind, Word, WordPart};
+
+
+// words that end a list when they start a command
+const TERMINATORS: [&str; 8] = ["}", "then", "elif", "else", "fi", "do", "done", "esac"];
+
+
+pub fn parse(line: &str) -> Result<List, SyntaxError> {
+    parse_with(line, &Aliases::new())
+}
+
+// aliases are replaced at the start of every command
+pub fn parse_with(line: &str, aliases: &Aliases) -> Result<List, SyntaxError> {
+    let tokens = tokenize(line)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end_col: line.chars().count() + 1,
+        aliases,
+        expanding: Vec::new(),
+    };
+    let list = parser.list()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.unexpected());
+    }
+    Ok(list)
+}
+
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    end_col: usize,
+    aliases: &'a Aliases,
+    expanding: Vec<(String, usize)>,  // alias, end of the tokens it was replaced by
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_op(&self) -> Option<Op> {
+        match self.peek() {
+            Some(Token { kind: TokenKind::Op(op), .. }) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn peek_reserved(&self, text: &str) -> bool {
+        matches!(self.peek().and_then(|t| t.as_word()), Some(w) if w.is_reserved(text))
+    }
+
+    // take the reserved word if it is next
+    fn eat(&mut self, text: &str) -> bool {
+        let found = self.peek_reserved(text);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), SyntaxError> {
+        match self.eat(text) {
+            true => Ok(()),
+            false => Err(self.unexpected()),
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek_op() == Some(Op::Newline) {
+            self.pos += 1;
+        }
+    }
+
+    fn unexpected(&self) -> SyntaxError {
+        match self.peek() {
+            Some(token) => SyntaxError::new(format!("unexpected token `{}`", token), token.col),
+            None => SyntaxError::incomplete("unexpected end of input", self.end_col),
+        }
+    }
+
+    // stop at end of input, `)`, `;;` or a word like `}` `fi` `done`
+    fn at_list_end(&self) -> bool {
+        self.peek().is_none()
+            || matches!(self.peek_op(), Some(Op::RParen) | Some(Op::DSemi))
+            || TERMINATORS.iter().any(|t| self.peek_reserved(t))
+    }
+
+    fn list(&mut self) -> Result<List, SyntaxError> {
+        let mut list = List::default();
+        loop {
+            self.skip_newlines();
+            if self.at_list_end() {
+                break;
+            }
+            let and_or = self.and_or()?;
+            let background = match self.peek_op() {
+                Some(Op::Amp) => true,
+                Some(Op::Semi) | Some(Op::Newline) => false,
+                _ => {
+                    list.items.push(ListItem { and_or, background: false });
+                    break;
+                }
+            };
+            self.pos += 1;
+            list.items.push(ListItem { and_or, background });
+        }
+        Ok(list)
+    }
+
+    fn and_or(&mut self) -> Result<AndOr, SyntaxError> {
+        let first = self.pipeline()?;
+        let mut rest = Vec::new();
+        loop {
+            let connector = match self.peek_op() {
+                Some(Op::And) => Connector::And,
+                Some(Op::Or) => Connector::Or,
+                _ => break,
+            };
+            self.pos += 1;
+            self.skip_newlines();
+            rest.push((connector, self.pipeline()?));
+        }
+        Ok(AndOr { first, rest })
+    }
+
+    fn pipeline(&mut self) -> Result<Pipeline, SyntaxError> {
+        let mut commands = vec![self.command()?];
+        while self.peek_op() == Some(Op::Pipe) {
+            self.pos += 1;
+            self.skip_newlines();
+            commands.push(self.command()?);
+        }
+        Ok(Pipeline { commands })
+    }
+
+    // an alias at the start of a command: the tokens of its text take the place of the word;
+    // inside those tokens the same alias is not replaced again, so alias ls='ls -a'
+    // and alias a='echo | a' end
+    fn expand_alias(&mut self) -> Result<(), SyntaxError> {
+        loop {
+            let pos = self.pos;
+            self.expanding.retain(|(_, end)| *end > pos);
+            let Some(Token { kind: TokenKind::Word(word), col }) = self.peek() else {
+                return Ok(());
+            };
+            let [WordPart::Text(name)] = word.parts.as_slice() else {
+                return Ok(());
+            };
+            if self.expanding.iter().any(|(n, _)| n == name) {
+                return Ok(());
+            }
+            let Some(text) = self.aliases.get(name) else {
+                return Ok(());
+            };
+            let (name, text, col) = (name.clone(), text.clone(), *col);
+            let mut tokens = tokenize(&text).map_err(|e| SyntaxError::new(format!("alias `{}`: {}", name, e.msg), col))?;
+            for token in &mut tokens {
+                token.col = col;
+            }
+            // one token becomes len tokens
+            let len = tokens.len();
+            for (_, end) in &mut self.expanding {
+                *end = *end + len - 1;
+            }
+            self.expanding.push((name, pos + len));
+            self.tokens.splice(pos..pos + 1, tokens);
+        }
+    }
+
+    fn command(&mut self) -> Result<Command, SyntaxError> {
+        self.expand_alias()?;
+        let kind = if self.peek_reserved("{") {
+            self.pos += 1;
+            let body = self.body()?;
+            if !self.peek_reserved("}") {
+                return Err(self.unexpected());
+            }
+            self.pos += 1;
+            CommandKind::Group(body)
+        } else if self.peek_op() == Some(Op::LParen) {
+            self.pos += 1;
+            let body = self.body()?;
+            if self.peek_op() != Some(Op::RParen) {
+                return Err(self.unexpected());
+            }
+            self.pos += 1;
+            CommandKind::Subshell(body)
+        } else if self.eat("if") {
+            CommandKind::If(self.if_clause()?)
+        } else if self.eat("while") {
+            CommandKind::While(self.loop_clause()?)
+        } else if self.eat("until") {
+            CommandKind::Until(self.loop_clause()?)
+        } else if self.eat("for") {
+            CommandKind::For(self.for_clause()?)
+        } else if self.eat("case") {
+            CommandKind::Case(self.case_clause()?)
+        } else if self.eat("function") {
+            let name = self.function_name(false).ok_or_else(|| self.unexpected())?;
+            return self.function(name);
+        } else if let Some(name) = self.function_name(true) {
+            return self.function(name);
+        } else {
+            return self.simple();
+        };
+
+        let mut redirects = Vec::new();
+        while let Some(redirect) = self.redirect()? {
+            redirects.push(redirect);
+        }
+        Ok(Command { kind, redirects })
+    }
+
+    // body of { } or ( ), must not be empty
+    fn body(&mut self) -> Result<List, SyntaxError> {
+        let body = self.list()?;
+        if body.items.is_empty() {
+            return Err(self.unexpected());
+        }
+        Ok(body)
+    }
+
+    // name(), or name after `function` where () may be left out
+    fn function_name(&mut self, parens: bool) -> Option<String> {
+        let name = match self.peek().and_then(|t| t.as_word()).map(|w| w.parts.as_slice()) {
+            Some([WordPart::Text(name)]) if !name.contains(['=', '{', '}', '$']) => name.clone(),
+            _ => return None,
+        };
+        let has_parens = self.tokens.get(self.pos + 1).is_some_and(|t| t.is_op(Op::LParen))
+            && self.tokens.get(self.pos + 2).is_some_and(|t| t.is_op(Op::RParen));
+        if parens && !has_parens {
+            return None;
+        }
+        self.pos += if has_parens { 3 } else { 1 };
+        Some(name)
+    }
+
+    // the body is a compound command: name() { ...; }
+    fn function(&mut self, name: String) -> Result<Command, SyntaxError> {
+        self.skip_newlines();
+        if matches!(self.peek().and_then(|t| t.as_word()), Some(w) if !["{", "if", "while", "until", "for", "case"].iter().any(|r| w.is_reserved(r))) {
+            return Err(self.unexpected());
+        }
+        let body = self.command()?;
+        Ok(Command {
+            kind: CommandKind::Function(Function { name, body: Box::new(body) }),
+            redirects: Vec::new(),
+        })
+    }
+
+    // after `if`
+    fn if_clause(&mut self) -> Result<If, SyntaxError> {
+        let mut clause = If::default();
+        loop {
+            let condition = self.body()?;
+            self.expect("then")?;
+            clause.branches.push((condition, self.body()?));
+            if !self.eat("elif") {
+                break;
+            }
+        }
+        if self.eat("else") {
+            clause.otherwise = Some(self.body()?);
+        }
+        self.expect("fi")?;
+        Ok(clause)
+    }
+
+    // after `while` or `until`
+    fn loop_clause(&mut self) -> Result<Loop, SyntaxError> {
+        let condition = self.body()?;
+        let body = self.do_group()?;
+        Ok(Loop { condition, body })
+    }
+
+    // do list done
+    fn do_group(&mut self) -> Result<List, SyntaxError> {
+        self.expect("do")?;
+        let body = self.body()?;
+        self.expect("done")?;
+        Ok(body)
+    }
+
+    // after `for`
+    fn for_clause(&mut self) -> Result<For, SyntaxError> {
+        let name = match self.peek().and_then(|t| t.as_word()).map(|w| w.parts.as_slice()) {
+            Some([WordPart::Text(name)]) if is_name(name) => name.clone(),
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        self.skip_newlines();
+
+        let mut words = None;
+        if self.eat("in") {
+            let mut list = Vec::new();
+            while let Some(word) = self.peek().and_then(|t| t.as_word()) {
+                list.push(word.clone());
+                self.pos += 1;
+            }
+            words = Some(list);
+            match self.peek_op() {
+                Some(Op::Semi) | Some(Op::Newline) => self.pos += 1,
+                _ => return Err(self.unexpected()),
+            }
+        } else if self.peek_op() == Some(Op::Semi) {
+            self.pos += 1;
+        }
+        self.skip_newlines();
+        let body = self.do_group()?;
+        Ok(For { name, words, body })
+    }
+
+    // after `case`
+    fn case_clause(&mut self) -> Result<Case, SyntaxError> {
+        let word = match self.peek().and_then(|t| t.as_word()) {
+            Some(word) => word.clone(),
+            None => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        self.skip_newlines();
+        self.expect("in")?;
+
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.eat("esac") {
+                break;
+            }
+            // (a | b) or a | b)
+            if self.peek_op() == Some(Op::LParen) {
+                self.pos += 1;
+            }
+            let mut patterns = Vec::new();
+            loop {
+                match self.peek().and_then(|t| t.as_word()) {
+                    Some(word) => patterns.push(word.clone()),
+                    None => return Err(self.unexpected()),
+                }
+                self.pos += 1;
+                match self.peek_op() {
+                    Some(Op::Pipe) => self.pos += 1,
+                    Some(Op::RParen) => break,
+                    _ => return Err(self.unexpected()),
+                }
+            }
+            self.pos += 1;
+            let body = self.list()?;
+            items.push(CaseItem { patterns, body });
+            // the last item may leave out ;;
+            if self.peek_op() == Some(Op::DSemi) {
+                self.pos += 1;
+            } else {
+                self.skip_newlines();
+                self.expect("esac")?;
+                break;
+            }
+        }
+        Ok(Case { word, items })
+    }
+
+    fn simple(&mut self) -> Result<Command, SyntaxError> {
+        let mut simple = SimpleCommand::default();
+        let mut redirects = Vec::new();
+        loop {
+            if let Some(redirect) = self.redirect()? {
+                redirects.push(redirect);
+                continue;
+            }
+            match self.peek().and_then(|t| t.as_word()) {
+                Some(word) => {
+                    match assignment(word) {
+                        Some(assign) if simple.words.is_empty() => simple.assigns.push(assign),
+                        _ => simple.words.push(word.clone()),
+                    }
+                    self.pos += 1;
+                }
+                None => break,
+            }
+        }
+        if simple.words.is_empty() && simple.assigns.is_empty() && redirects.is_empty() {
+            return Err(self.unexpected());
+        }
+        Ok(Command {
+            kind: CommandKind::Simple(simple),
+            redirects,
+        })
+    }
+
+    fn redirect(&mut self) -> Result<Option<Redirect>, SyntaxError> {
+        let fd = match self.peek() {
+            Some(Token { kind: TokenKind::IoNumber(fd), .. }) => {
+                let fd = *fd;
+                self.pos += 1;
+                Some(fd)
+            }
+            _ => None,
+        };
+        let op = match self.peek_op() {
+            Some(op) if op.is_redirect() => op,
+            _ if fd.is_some() => return Err(self.unexpected()),
+            _ => return Ok(None),
+        };
+        self.pos += 1;
+        let input = matches!(op, Op::Less | Op::DLess | Op::DLessDash | Op::TLess);
+        let fd = fd.unwrap_or(if input { 0 } else { 1 });
+        match self.peek().and_then(|t| t.as_word()) {
+            Some(target) => {
+                let target = target.clone();
+                self.pos += 1;
+                Ok(Some(Redirect { fd, op, target }))
+            }
+            None => Err(self.unexpected()),
+        }
+    }
+}
+
+// NAME=value: the = must be unquoted, a ~ right after it is expanded too
+fn assignment(word: &Word) -> Option<(String, Word)> {
+    let Some(WordPart::Text(first)) = word.parts.first() else {
+        return None;
+    };
+    let (name, rest) = first.split_once('=')?;
+    if !is_name(name) {
+        return None;
+    }
+    let mut value = Word::default();
+    match rest.strip_prefix('~') {
+        Some(tail) if tail.split('/').next().is_some_and(|user| user.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+            && (tail.contains('/') || word.parts.len() == 1) => {
+            let user = tail.split('/').next().unwrap_or_default();
+            value.parts.push(WordPart::Tilde(user.to_string()));
+            if tail.len() > user.len() {
+                value.parts.push(WordPart::Text(tail[user.len()..].to_string()));
+            }
+        }
+        _ if !rest.is_empty() => value.parts.push(WordPart::Text(rest.to_string())),
+        _ => {}
+    }
+    value.parts.extend(word.parts[1..].iter().cloned());
+    Some((name.to_string(), value))
+}
+
+// a variable name: letters, digits and _, not starting with a digit
+pub fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -1,5 +1,7 @@
+use crate::commands::registry;
 
-#[derive(Debug, PartialEq, PartialOrd, Eq,Clone)]
+
+#[derive(Debug, PartialEq, Eq,Clone)]
 pub enum CommandPriority {
     Low,
     Medium,
@@ -18,18 +20,22 @@ impl CommandPriority{
     }
 }
 
+impl PartialOrd for CommandPriority {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for CommandPriority {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.as_number().cmp(&other.as_number())
     }
 }
 
-// Set command priority
+// Set command priority, Builtin::with_priority() in commands/builtins.rs
 pub fn get_priority(command: &str) -> CommandPriority{
-    match command{
-        "pwd"|"ls"|"mkdir"|"touch"|"whoami"|"exit"|"echo"|"print" => CommandPriority::Low,
-        "cd"|"rm"|"cat"|"python"|"html"|"web"|"rn"|"mv"|"tar"|"grep"|"pd"|"root"|"apt"|"history" => CommandPriority::Medium,
-        "sleep"|"kill"|"ps"=> CommandPriority::High,
-        _ => CommandPriority::Unknow
+    match registry::find(command){
+        Some(command) => command.priority(),
+        None => CommandPriority::Unknow
     }
-}
\ No newline at end of file
+}
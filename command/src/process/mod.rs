@@ -5,6 +5,7 @@ use process::ProcessState;
 
 use self::thread::ThreadControlBlock;
 
+#[allow(clippy::module_inception)]
 pub mod process;
 pub mod add_task;
 pub mod thread;
@@ -19,7 +20,7 @@ pub fn sleep(tcb:&mut ThreadControlBlock,time: usize) -> String{
 }
 
 pub fn ps() -> String{
-    let mut output = format!(" ");
+    let mut output = " ".to_string();
     let s = RUNNING_P.lock().unwrap();
     for map in s.iter() {
         for (pid, (name, state)) in map {
@@ -67,6 +67,12 @@ impl Drop for ProcessManager{
     }
 }
 
+impl Default for ProcessManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ProcessManager {
     pub fn new() -> Self {
         ProcessManager {
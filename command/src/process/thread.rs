@@ -66,6 +66,12 @@ impl Drop for ThreadControlBlock{
     }
 }
 
+impl Default for ThreadControlBlock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ThreadControlBlock {
     pub fn new() -> ThreadControlBlock {
         ThreadControlBlock {
@@ -0,0 +1,291 @@
+// stdin / stdout / stderr of the running command
+// every thread keeps its current streams, redirects of a command start
+// from them, so `{ ...; } 2> err` also applies to the commands inside
+// stages of `a | b` run in their own threads, connected by OS pipes
+//
+// commands write through these streams only: success to stdout, failure and
+// messages to stderr, so an embedder can give its own Streams to
+// run_command_with and capture each one
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Cursor, PipeReader, PipeWriter, Read, Write};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::parse::lexer::Op;
+
+
+pub enum Input {
+    Stdin,
+    File(File),
+    Text(String),  // here-document, here-string
+    Pipe(PipeReader),
+}
+
+pub enum Output {
+    Stdout,
+    Stderr,
+    File(File),
+    Pipe(PipeWriter),
+    Buffer(Buffer),  // kept in memory
+}
+
+// bytes written to an Output::Buffer, shared by its clones
+#[derive(Clone,Default)]
+pub struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Buffer {
+    pub fn new() -> Buffer {
+        Buffer::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).to_string()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Streams {
+    pub stdin: Input,
+    pub stdout: Output,
+    pub stderr: Output,
+}
+
+impl Default for Streams {
+    fn default() -> Self {
+        Streams {
+            stdin: Input::Stdin,
+            stdout: Output::Stdout,
+            stderr: Output::Stderr,
+        }
+    }
+}
+
+impl Input {
+    pub fn try_clone(&self) -> io::Result<Input> {
+        match self {
+            Input::Stdin => Ok(Input::Stdin),
+            Input::File(f) => Ok(Input::File(f.try_clone()?)),
+            Input::Text(s) => Ok(Input::Text(s.clone())),
+            Input::Pipe(p) => Ok(Input::Pipe(p.try_clone()?)),
+        }
+    }
+
+    pub fn to_stdio(&self) -> io::Result<process::Stdio> {
+        match self {
+            Input::Stdin => Ok(process::Stdio::inherit()),
+            Input::File(f) => Ok(process::Stdio::from(f.try_clone()?)),
+            Input::Text(s) => {
+                // feed the text through a pipe
+                let (reader, mut writer) = io::pipe()?;
+                let text = s.clone();
+                thread::spawn(move || {
+                    let _ = writer.write_all(text.as_bytes());
+                });
+                Ok(process::Stdio::from(reader))
+            }
+            Input::Pipe(p) => Ok(process::Stdio::from(p.try_clone()?)),
+        }
+    }
+}
+
+impl Output {
+    pub fn try_clone(&self) -> io::Result<Output> {
+        match self {
+            Output::Stdout => Ok(Output::Stdout),
+            Output::Stderr => Ok(Output::Stderr),
+            Output::File(f) => Ok(Output::File(f.try_clone()?)),
+            Output::Pipe(p) => Ok(Output::Pipe(p.try_clone()?)),
+            Output::Buffer(b) => Ok(Output::Buffer(b.clone())),
+        }
+    }
+
+    // a buffer is filled from a pipe by spawn()
+    pub fn to_stdio(&self) -> io::Result<process::Stdio> {
+        match self {
+            Output::Stdout => Ok(process::Stdio::inherit()),
+            Output::Stderr => Ok(process::Stdio::from(io::stderr())),
+            Output::File(f) => Ok(process::Stdio::from(f.try_clone()?)),
+            Output::Pipe(p) => Ok(process::Stdio::from(p.try_clone()?)),
+            Output::Buffer(_) => Ok(process::Stdio::piped()),
+        }
+    }
+
+    pub fn writer(&self) -> io::Result<Box<dyn Write + Send>> {
+        match self {
+            Output::Stdout => Ok(Box::new(io::stdout())),
+            Output::Stderr => Ok(Box::new(io::stderr())),
+            Output::File(f) => Ok(Box::new(f.try_clone()?)),
+            Output::Pipe(p) => Ok(Box::new(p.try_clone()?)),
+            Output::Buffer(b) => Ok(Box::new(b.clone())),
+        }
+    }
+}
+
+impl Streams {
+    pub fn try_clone(&self) -> io::Result<Streams> {
+        Ok(Streams {
+            stdin: self.stdin.try_clone()?,
+            stdout: self.stdout.try_clone()?,
+            stderr: self.stderr.try_clone()?,
+        })
+    }
+
+    // apply one redirect, `target` is already expanded
+    pub fn redirect(&mut self, fd: usize, op: Op, target: &str) -> Result<(), String> {
+        let open_err = |e: io::Error| format!("Error: {}: {}", target, e);
+        match op {
+            Op::Less | Op::DLess | Op::DLessDash | Op::TLess => {
+                if fd != 0 {
+                    return Err(format!("Error: {}: bad file descriptor", fd));
+                }
+                self.stdin = match op {
+                    Op::Less => Input::File(File::open(target).map_err(open_err)?),
+                    Op::TLess => Input::Text(format!("{}\n", target)),
+                    _ => Input::Text(target.to_string()),
+                };
+            }
+            Op::Great | Op::DGreat => {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(op == Op::DGreat)
+                    .truncate(op == Op::Great)
+                    .open(target)
+                    .map_err(open_err)?;
+                *self.output(fd)? = Output::File(file);
+            }
+            Op::GreatAnd => {
+                // 2>&1  >&2
+                let from = match target.parse::<usize>() {
+                    Ok(n) => n,
+                    Err(_) => return Err(format!("Error: {}: ambiguous redirect", target)),
+                };
+                let output = self.output(from)?.try_clone().map_err(open_err)?;
+                *self.output(fd)? = output;
+            }
+            Op::AndGreat | Op::AndDGreat => {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(op == Op::AndDGreat)
+                    .truncate(op == Op::AndGreat)
+                    .open(target)
+                    .map_err(open_err)?;
+                self.stderr = Output::File(file.try_clone().map_err(open_err)?);
+                self.stdout = Output::File(file);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn output(&mut self, fd: usize) -> Result<&mut Output, String> {
+        match fd {
+            1 => Ok(&mut self.stdout),
+            2 => Ok(&mut self.stderr),
+            _ => Err(format!("Error: {}: bad file descriptor", fd)),
+        }
+    }
+}
+
+
+thread_local! {
+    static CURRENT: RefCell<Streams> = RefCell::new(Streams::default());
+}
+
+// a copy of the current streams
+pub fn current() -> io::Result<Streams> {
+    CURRENT.with(|c| c.borrow().try_clone())
+}
+
+// run f with `streams` as the current streams
+pub fn scoped<T>(streams: Streams, f: impl FnOnce() -> T) -> T {
+    let old = CURRENT.with(|c| c.replace(streams));
+    let res = f();
+    CURRENT.with(|c| c.replace(old));
+    res
+}
+
+// run a child process with the current streams, returns when it exits
+pub fn spawn(cmd: &mut process::Command) -> io::Result<process::ExitStatus> {
+    let streams = current()?;
+    cmd.stdin(streams.stdin.to_stdio()?)
+        .stdout(streams.stdout.to_stdio()?)
+        .stderr(streams.stderr.to_stdio()?);
+    let mut child = cmd.spawn()?;
+
+    // copy what goes to a buffer while the child runs
+    let mut copies = Vec::new();
+    if let (Output::Buffer(b), Some(out)) = (&streams.stdout, child.stdout.take()) {
+        copies.push(copy_to(out, b.clone()));
+    }
+    if let (Output::Buffer(b), Some(err)) = (&streams.stderr, child.stderr.take()) {
+        copies.push(copy_to(err, b.clone()));
+    }
+    let status = child.wait();
+    for copy in copies {
+        let _ = copy.join();
+    }
+    status
+}
+
+fn copy_to(mut from: impl Read + Send + 'static, mut to: Buffer) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let _ = io::copy(&mut from, &mut to);
+    })
+}
+
+// reader of `< file`, `<<EOF`, `<<<` or `a |`, None when stdin is the terminal
+pub fn stdin() -> Option<Box<dyn Read + Send>> {
+    CURRENT.with(|c| -> Option<Box<dyn Read + Send>> {
+        match &c.borrow().stdin {
+            Input::File(f) => Some(Box::new(f.try_clone().ok()?)),
+            Input::Text(s) => Some(Box::new(Cursor::new(s.clone()))),
+            Input::Pipe(p) => Some(Box::new(p.try_clone().ok()?)),
+            Input::Stdin => None,
+        }
+    })
+}
+
+// writer of `> file` or `| b`, None when stdout is the terminal
+pub fn stdout() -> Option<Box<dyn Write + Send>> {
+    CURRENT.with(|c| match &c.borrow().stdout {
+        Output::Stdout => None,
+        output => output.writer().ok(),
+    })
+}
+
+// writers of the current stdout and stderr, the terminal too
+pub fn out() -> Box<dyn Write + Send> {
+    CURRENT.with(|c| c.borrow().stdout.writer()).unwrap_or_else(|_| Box::new(io::stdout()))
+}
+
+pub fn err() -> Box<dyn Write + Send> {
+    CURRENT.with(|c| c.borrow().stderr.writer()).unwrap_or_else(|_| Box::new(io::stderr()))
+}
+
+// output of a builtin: success goes to stdout, failure to stderr
+pub fn write_result(res: (usize, String)) -> (usize, String) {
+    let (status, text) = res;
+    if !text.is_empty() {
+        let mut writer = if status == 0 { out() } else { err() };
+        let _ = writer.write_all(text.as_bytes());
+        if !text.ends_with('\n') {
+            let _ = writer.write_all(b"\n");
+        }
+        let _ = writer.flush();
+    }
+    (status, String::new())
+}
@@ -1,8 +1,10 @@
 // build root
+#[derive(Clone)]
 pub struct UserState{
     pub root: UserRole
 }
 
+#[derive(Clone,PartialEq)]
 pub enum UserRole {
     User,
     Admin,
@@ -18,8 +20,10 @@ impl UserRole{
 }
 
 
-pub struct Root {
-    pub allowed_commands: Vec<String>,
+impl Default for UserState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UserState {
@@ -44,6 +48,7 @@ use std::fs;
 use std::process::Command;
 
 
+#[derive(Clone)]
 pub struct User{
     pub username: String,
     pub password: String,
@@ -64,11 +69,11 @@ impl User{
         self.has_set_password = true;
     }
 
-    pub fn revise_password(&self, password: &str) -> Result<(usize,String), std::io::Error> {
+    pub fn revise_password(&self, password: &str) -> TiksResult {
         let new_pd = encryption(password.to_string());
         let _ = self.password == new_pd;
 
-        let output = format!("Successfully revises the password");
+        let output = "Successfully revises the password".to_string();
         Ok((STATUE_CODE,output))
     }
 
@@ -95,27 +100,90 @@ impl User{
 }
 
 
+// set -o ..., shopt -s ...
+#[derive(Clone,Default)]
+pub struct Options{
+    pub nounset: bool,  // -u: an unset variable is an error
+    pub noglob: bool,   // -f: * ? [ are plain text
+    pub nullglob: bool,  // a pattern matching nothing is removed
+    pub failglob: bool,  // a pattern matching nothing is an error
+    pub dotglob: bool,   // * matches names starting with . too
+}
+
+impl Options{
+    pub fn names() -> [&'static str; 5]{
+        ["nounset", "noglob", "nullglob", "failglob", "dotglob"]
+    }
+
+    pub fn get(&self, name: &str) -> Option<bool>{
+        match name{
+            "nounset" => Some(self.nounset),
+            "noglob" => Some(self.noglob),
+            "nullglob" => Some(self.nullglob),
+            "failglob" => Some(self.failglob),
+            "dotglob" => Some(self.dotglob),
+            _ => None,
+        }
+    }
+
+    // false for an unknown name
+    pub fn set(&mut self, name: &str, on: bool) -> bool{
+        match name{
+            "nounset" => self.nounset = on,
+            "noglob" => self.noglob = on,
+            "nullglob" => self.nullglob = on,
+            "failglob" => self.failglob = on,
+            "dotglob" => self.dotglob = on,
+            _ => return false,
+        }
+        true
+    }
+
+    // name on/off, one line each
+    pub fn list(&self, names: &[&str]) -> String{
+        names.iter()
+        .map(|name| format!("{:<12}{}", name, if self.get(name) == Some(true) { "on" } else { "off" }))
+        .collect::<Vec<String>>()
+        .join("\n")
+    }
+}
+
+// name() { ... } defined in this session
+pub type Functions = HashMap<String,ast::Command>;
+
+#[derive(Clone)]
 pub struct SessionContext {
-    pub root: Root,
     pub user_state: UserState,
-    pub user: User
+    pub user: User,
+    pub last_status: usize,  // $?
+    pub flow: Flow,  // set by break, continue and return
+    pub loop_depth: usize,
+    pub sourcing: usize,  // files being run by `source`, inside each other
+    pub subshell: usize,  // ( ), $( ) and pipeline stages around: exit ends only them
+    pub functions: Functions,
+    pub shell_name: String,  // $0: the script, NAME of `tiks -c COMMAND NAME`, or tiks
+    pub args: Vec<String>,  // $1 $2 ..., of the script or the function
+    pub locals: Vec<Vec<(String,Option<String>)>>,  // one frame per function call: old values
+    pub options: Options,
+    pub vars: HashMap<String,String>,  // X=1, not in the environment until `export X`
+    pub env: Option<HashMap<String,Option<String>>>,  // in ( ), $( ) and pipeline stages: their exports and unsets, the process's environment stays
+    pub stage: bool,  // a pipeline stage running beside the others: cd does not change the directory
+    pub aliases: Aliases,
+}
+
+impl Default for SessionContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SessionContext{
     pub fn new() -> SessionContext{
-        let root = Root{
-            allowed_commands: vec![
-            "mkdir".to_string(),"rm".to_string(),"rn".to_string(),
-            "touch".to_string(),"cat".to_string(),"mv".to_string(),
-            "pd".to_string()], // add command in root
-        };
-        let userstate = UserState::new(); //false
-
         let home_dir = dirs::home_dir().expect("Failed to get home directory");
         let binding = home_dir.join(".Tiks").join("tiks");  // create dir in setup.sh
         let user_file_path = binding.as_os_str().to_str().unwrap();
 
-        let user = match User::load_from_file(&user_file_path){
+        let user = match User::load_from_file(user_file_path){
             Ok(res) =>{
                  if !res.has_set_password() {
                     let mut username = String::new();
@@ -124,7 +192,7 @@ impl SessionContext{
                     let password = get_password();
     
                     let user = User::new(username, password, false);
-                    user.save_to_file(&user_file_path).expect("Failed to save user");
+                    user.save_to_file(user_file_path).expect("Failed to save user");
                     user
                 }else{
                     res
@@ -145,17 +213,105 @@ impl SessionContext{
                 let pd = encryption(password);
 
                 let user = User::new(user, pd, false);
-                user.save_to_file(&user_file_path).expect("Failed to save user");
+                user.save_to_file(user_file_path).expect("Failed to save user");
                 user
             }
         };
-        
+        let mut session = SessionContext::with_user(user);
+        session.aliases = alias::load();
+        session
+    }
+
+    // a session for this user, without reading ~/.Tiks/tiks
+    pub fn with_user(user: User) -> SessionContext{
+        let userstate = UserState::new(); //false
+
         SessionContext{
-            root, 
             user_state: userstate,
-            user
+            user,
+            last_status: STATUE_CODE,
+            flow: Flow::Normal,
+            loop_depth: 0,
+            sourcing: 0,
+            subshell: 0,
+            functions: Functions::new(),
+            shell_name: "tiks".to_string(),
+            args: Vec::new(),
+            locals: Vec::new(),
+            options: Options::default(),
+            vars: HashMap::new(),
+            env: None,
+            stage: false,
+            aliases: Aliases::new(),
         }
     }
+
+    // shell variables first, then the environment
+    pub fn get_var(&self, name: &str) -> Option<String>{
+        self.vars.get(name).cloned().or_else(|| self.env_var(name))
+    }
+
+    // an exported variable stays exported
+    pub fn set_var(&mut self, name: &str, value: &str){
+        if !self.vars.contains_key(name) && self.env_var(name).is_some(){
+            self.set_env(name, value);
+        }else{
+            self.vars.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    pub fn unset_var(&mut self, name: &str){
+        self.vars.remove(name);
+        if self.env_var(name).is_some(){
+            self.remove_env(name);
+        }
+    }
+
+    // the environment of programs: the changes of this copy, then the process's
+    pub fn env_var(&self, name: &str) -> Option<String>{
+        match self.env.as_ref().and_then(|env| env.get(name)){
+            Some(value) => value.clone(),
+            None => env::var(name).ok(),
+        }
+    }
+
+    pub fn set_env(&mut self, name: &str, value: &str){
+        match &mut self.env{
+            Some(env) => { env.insert(name.to_string(), Some(value.to_string())); }
+            None => {
+                env::set_var(name, value);
+                if name == "PATH"{
+                    clear_path_cache();
+                }
+            }
+        }
+    }
+
+    pub fn remove_env(&mut self, name: &str){
+        match &mut self.env{
+            Some(env) => { env.insert(name.to_string(), None); }
+            None => {
+                env::remove_var(name);
+                if name == "PATH"{
+                    clear_path_cache();
+                }
+            }
+        }
+    }
+
+    // every exported variable, sorted by name
+    pub fn environment(&self) -> Vec<(String,String)>{
+        let mut vars: HashMap<String,String> = env::vars().collect();
+        for (name, value) in self.env.iter().flatten(){
+            match value{
+                Some(value) => vars.insert(name.clone(), value.clone()),
+                None => vars.remove(name),
+            };
+        }
+        let mut vars: Vec<(String,String)> = vars.into_iter().collect();
+        vars.sort();
+        vars
+    }
     pub fn get_username(&self) -> String{
         let name = self.user.username.clone().trim().to_string();
         name
@@ -169,7 +325,7 @@ impl SessionContext{
             self.user_state.toggle_root();
             Ok(())
         } else {
-            Err(io::Error::new(io::ErrorKind::Other, "Incorrect password"))
+            Err(io::Error::other("Incorrect password"))
         }
     }
 }
@@ -189,7 +345,7 @@ fn get_password() -> String{
         if pd == pd_again{
             return pd
         }else {
-            eprint!("Error: password isn't same\n");
+            eprintln!("Error: password isn't same");
             continue;
         }
     }
@@ -202,12 +358,19 @@ use base64::{encode, decode};
 use lazy_static::lazy_static;
 
 use crate::start::state_code::STATUE_CODE;
+use crate::start::error::TiksResult;
+use crate::commands::alias::{self, Aliases};
+use crate::run::Flow;
+use crate::parse::ast;
+use crate::env::clear_path_cache;
+use std::collections::HashMap;
+use std::env;
 // base64
 // 加密
 #[allow(deprecated)]
 fn encryption(pd: String) -> String{
-    let res = encode(pd.clone());
-    res
+    
+    encode(pd.clone())
 }
 
 // 解密
@@ -220,8 +383,8 @@ pub fn decryption(pd: String) -> String{
             password
         },
         Err(_)=>{
-            let output = format!("The password does not exist");
-            output
+            
+            "The password does not exist".to_string()
         }
     }
 }
@@ -235,7 +398,7 @@ lazy_static!{
 fn init_setup_linux(){
     Command::new("bash")
     .arg("./mac_linux/setup.sh")
-    .spawn()
+    .status()
     .expect("Error: Can't setup");
 }
 
@@ -243,13 +406,13 @@ fn init_setup_linux(){
 fn init_setup_mac() {
     Command::new("bash")
         .arg("./mac_linux/setup.sh")
-        .spawn()
+        .status()
         .expect("Error: Can't setup on macOS");
 }
 
 #[cfg(target_os="windows")]
 fn init_setup_windows() {
     Command::new("./window/setup.bat")
-        .spawn()
+        .status()
         .expect("Error: Can't setup on Windows");
 }
\ No newline at end of file
@@ -2,21 +2,31 @@ use crate::commands::command::*;
 use crate::priority::{get_priority, CommandPriority};
 use crate::set::set::{error_log, get_last};
 use crate::process::process::{ProcessManager, ProcessState};
-use crate::process::{ps, sleep, RUNNING_P};
+use crate::process::RUNNING_P;
 use crate::process::thread::ThreadControlBlock;
 use crate::process::add_task::{add_command_to_thread,add_thread_to_process};
 use crate::root::SessionContext;
 use crate::signal::semaphore_new;
-use crate::commands::arg::{command_match, split, Commands};
+use crate::start::error::{TiksError, TiksResult};
+use crate::start::state_code::{INTERRUPTED, MISSING_PARAMETERS, NOT_EXIST, STATUE_CODE};
+use crate::commands::arg::{command_match, Commands};
+use crate::parse::ast::{AndOr, Case, Command, CommandKind, For, If, List, Loop};
+use crate::parse::parser::parse_with;
+use crate::expand::{expand_fields, expand_pattern, expand_word, pattern};
+use crate::redirect::{self, Buffer, Output, Streams};
+use crate::parse::lexer::WordPart;
 use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 static NEXT_TID: AtomicUsize = AtomicUsize::new(200);
 static NEXT_PID: AtomicUsize = AtomicUsize::new(200);
 
 
-pub fn handle_command(args: Vec<String>) -> (Commands,usize,usize,CommandPriority) {
-    let commands = Commands::new(args.clone());
+pub fn handle_command(commands: Commands) -> (Commands,usize,usize,CommandPriority) {
     let command = commands.command.clone();
     let priority = get_priority(command.as_str());
 
@@ -28,16 +38,336 @@ pub fn handle_command(args: Vec<String>) -> (Commands,usize,usize,CommandPriorit
 }
 
 
-pub fn run<T>(input: T,session_context: &mut SessionContext) -> (usize,String)
-where Vec<String>: From<T>
-{
-    let (commands,pid,tid,priority) = handle_command(input.into());
+// break n and continue n, read by the loops around
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Flow{
+    Normal,
+    Break(usize),
+    Continue(usize),
+    Return(usize),
+    Exit(usize),  // exit in ( ), $( ) or a pipeline stage: only that one ends
+}
+
+// calls inside calls, deeper is an error
+const MAX_CALL_DEPTH: usize = 256;
+
+
+// list -> and_or -> pipeline, `&` items are collected and run by priority
+// stops after a break or continue
+pub fn run_list(list: &List, session_context: &mut SessionContext) -> Vec<(usize,String)>{
+    let mut output = Vec::new();
+    let mut batch: Vec<&AndOr> = Vec::new();
+    for item in &list.items{
+        batch.push(&item.and_or);
+        if item.background{
+            continue;
+        }
+        if batch.len()==1{
+            output.extend(and(batch[0], session_context));
+        }else{
+            output.extend(priority_run(&batch, session_context));
+        }
+        batch.clear();
+        if session_context.flow != Flow::Normal{
+            return output;
+        }
+    }
+    if !batch.is_empty(){
+        output.extend(priority_run(&batch, session_context));
+    }
+    output
+}
+
+
+// run one command of the tree
+pub fn run(command: &Command, session_context: &mut SessionContext) -> (usize,String){
+    let res = match open_redirects(command, session_context){
+        Ok(streams) => redirect::scoped(streams, || run_kind(&command.kind, session_context)),
+        Err(e) => redirect::write_result((NOT_EXIST,e)),
+    };
+    session_context.last_status = res.0;
+    res
+}
+
+// current streams + the redirects of this command, left to right
+fn open_redirects(command: &Command, session_context: &mut SessionContext) -> Result<Streams,String>{
+    let mut streams = redirect::current().map_err(|e| format!("Error: {}",e))?;
+    for r in &command.redirects{
+        let target = expand_word(&r.target, session_context).map_err(|e| e.to_string())?;
+        streams.redirect(r.fd, r.op, &target)?;
+    }
+    Ok(streams)
+}
+
+fn run_kind(kind: &CommandKind, session_context: &mut SessionContext) -> (usize,String){
+    match kind{
+        CommandKind::Simple(simple) => {
+            let mut assigns = Vec::new();
+            for (name, word) in &simple.assigns{
+                match expand_word(word, session_context){
+                    Ok(value) => assigns.push((name.clone(), value)),
+                    Err(e) => return redirect::write_result(e.into()),
+                }
+            }
+            let mut words = Vec::new();
+            for word in &simple.words{
+                match expand_fields(word, session_context){
+                    Ok(fields) => words.extend(fields),
+                    Err(e) => return redirect::write_result(e.into()),
+                }
+            }
+            // X=1 alone sets a shell variable, `> file` alone only creates the file,
+            // "$@" of no arguments leaves nothing to run
+            if words.is_empty(){
+                for (name, value) in &assigns{
+                    session_context.set_var(name, value);
+                }
+                // X=$(cmd) has the status of cmd
+                let substituted = simple.assigns.iter().any(|(_, w)| w.parts.iter().any(|p| matches!(p, WordPart::Command { .. })));
+                return (if substituted { session_context.last_status } else { STATUE_CODE },String::new());
+            }
+            let commands = Commands::new(words);
+            with_env(&assigns, session_context, |s| redirect::write_result(run_commands(commands, s)))
+        }
+        CommandKind::Group(list) => group_output(run_list(list, session_context)),
+        CommandKind::Subshell(list) => subshell(session_context, |inner| {
+            let mut res = group_output(run_list(list, inner));
+            // a break or an exit ends only the ( )
+            if let Flow::Exit(n) = inner.flow{
+                res.0 = n;
+            }
+            res
+        }),
+        CommandKind::If(clause) => run_if(clause, session_context),
+        CommandKind::While(l) => run_loop(l, false, session_context),
+        CommandKind::Until(l) => run_loop(l, true, session_context),
+        CommandKind::For(clause) => run_for(clause, session_context),
+        CommandKind::Case(clause) => run_case(clause, session_context),
+        CommandKind::Function(f) => {
+            session_context.functions.insert(f.name.clone(), (*f.body).clone());
+            (STATUE_CODE,String::new())
+        }
+    }
+}
+
+// ( ) and $( ) run on a copy of the session: variables, functions, options, aliases,
+// exports and the directory set inside stay inside, only the status comes out
+fn subshell<T>(session_context: &mut SessionContext, f: impl FnOnce(&mut SessionContext) -> T) -> T{
+    let mut inner = session_context.clone();
+    inner.flow = Flow::Normal;
+    inner.subshell += 1;
+    inner.env.get_or_insert_with(HashMap::new);
+    // in a pipeline stage cd did not move, and the other stages may have
+    let cwd = env::current_dir().ok().filter(|_| !inner.stage);
+    let res = f(&mut inner);
+    if let Some(cwd) = cwd{
+        let _ = env::set_current_dir(cwd);
+    }
+    session_context.last_status = inner.last_status;
+    res
+}
+
+// $(text) and `text`: the stdout of text without the trailing newlines
+pub fn substitute(text: &str, session_context: &mut SessionContext) -> Result<String,TiksError>{
+    let list = parse_with(text, &session_context.aliases).map_err(|e| TiksError::usage(format!("syntax error: {}", e.msg)))?;
+    let buffer = Buffer::new();
+    let mut streams = redirect::current()?;
+    streams.stdout = Output::Buffer(buffer.clone());
+
+    subshell(session_context, |inner| {
+        inner.loop_depth = 0;
+        redirect::scoped(streams, || {
+            for (_status,r) in run_list(&list, inner){
+                if !r.is_empty(){
+                    let _ = writeln!(redirect::out(), "{}", r);
+                }
+            }
+        });
+    });
+    Ok(buffer.contents().trim_end_matches('\n').to_string())
+}
+
+// FOO=bar cmd: the values are in the environment of cmd only, then put back
+pub fn with_env<T>(assigns: &[(String,String)], session_context: &mut SessionContext, f: impl FnOnce(&mut SessionContext) -> T) -> T{
+    let saved: Vec<_> = assigns.iter()
+        .map(|(name, _)| (name.clone(), session_context.vars.remove(name), session_context.env_var(name)))
+        .collect();
+    for (name, value) in assigns{
+        session_context.set_env(name, value);
+    }
+
+    let res = f(session_context);
+
+    for (name, var, exported) in saved.into_iter().rev(){
+        match exported{
+            Some(value) => session_context.set_env(&name, &value),
+            None => session_context.remove_env(&name),
+        }
+        if let Some(value) = var{
+            session_context.vars.insert(name, value);
+        }
+    }
+    res
+}
+
+// name args...: $1 ... are the arguments while the body runs,
+// `return n` gives the status and `local` values are put back after
+pub fn call_function(body: &Command, args: Vec<String>, session_context: &mut SessionContext) -> TiksResult{
+    if session_context.locals.len() >= MAX_CALL_DEPTH{
+        return Err(TiksError::usage(format!("maximum function nesting level exceeded ({})",MAX_CALL_DEPTH)));
+    }
+    let saved_args = std::mem::replace(&mut session_context.args, args);
+    let saved_depth = std::mem::replace(&mut session_context.loop_depth, 0);
+    session_context.locals.push(Vec::new());
+
+    let mut res = run(body, session_context);
+    if let Flow::Return(n) = session_context.flow{
+        res.0 = n;
+        session_context.flow = Flow::Normal;
+    }
+
+    for (name, old) in session_context.locals.pop().unwrap_or_default().into_iter().rev(){
+        match old{
+            Some(value) => session_context.vars.insert(name, value),
+            None => session_context.vars.remove(&name),
+        };
+    }
+    session_context.loop_depth = saved_depth;
+    session_context.args = saved_args;
+    Ok(res)
+}
+
+// the first branch whose condition succeeds, else the else branch
+fn run_if(clause: &If, session_context: &mut SessionContext) -> (usize,String){
+    for (condition, body) in &clause.branches{
+        run_list(condition, session_context);
+        if session_context.flow != Flow::Normal{
+            return (session_context.last_status,String::new());
+        }
+        if session_context.last_status == STATUE_CODE{
+            return group_output(run_list(body, session_context));
+        }
+    }
+    match &clause.otherwise{
+        Some(body) => group_output(run_list(body, session_context)),
+        None => (STATUE_CODE,String::new()),
+    }
+}
+
+// while: run the body as long as the condition succeeds, until: fails
+fn run_loop(l: &Loop, until: bool, session_context: &mut SessionContext) -> (usize,String){
+    let mut status = STATUE_CODE;
+    session_context.loop_depth += 1;
+    loop{
+        run_list(&l.condition, session_context);
+        if session_context.flow != Flow::Normal{
+            loop_done(session_context);
+            break;
+        }
+        if (session_context.last_status == STATUE_CODE) == until{
+            break;
+        }
+        status = group_output(run_list(&l.body, session_context)).0;
+        if loop_done(session_context){
+            break;
+        }
+    }
+    session_context.loop_depth -= 1;
+    (status,String::new())
+}
+
+// for name in words: the name is set to each word in turn
+fn run_for(clause: &For, session_context: &mut SessionContext) -> (usize,String){
+    let mut words = Vec::new();
+    match &clause.words{
+        Some(list) => for word in list{
+            match expand_fields(word, session_context){
+                Ok(fields) => words.extend(fields),
+                Err(e) => return redirect::write_result(e.into()),
+            }
+        },
+        None => words = session_context.args.clone(),
+    }
+    let mut status = STATUE_CODE;
+    session_context.loop_depth += 1;
+    for word in words{
+        session_context.set_var(&clause.name, &word);
+        status = group_output(run_list(&clause.body, session_context)).0;
+        if loop_done(session_context){
+            break;
+        }
+    }
+    session_context.loop_depth -= 1;
+    (status,String::new())
+}
+
+// the body of the first item with a matching pattern
+fn run_case(clause: &Case, session_context: &mut SessionContext) -> (usize,String){
+    let word = match expand_word(&clause.word, session_context){
+        Ok(word) => word,
+        Err(e) => return redirect::write_result(e.into()),
+    };
+    for item in &clause.items{
+        for p in &item.patterns{
+            match expand_pattern(p, session_context){
+                Ok(p) if pattern::matches(&p, &word) => return group_output(run_list(&item.body, session_context)),
+                Ok(_) => {}
+                Err(e) => return redirect::write_result(e.into()),
+            }
+        }
+    }
+    (STATUE_CODE,String::new())
+}
+
+// after the body of a loop: true when the loop ends here
+// break 2 and continue 2 are passed on to the loop around
+fn loop_done(session_context: &mut SessionContext) -> bool{
+    match session_context.flow{
+        Flow::Break(n) => {
+            session_context.flow = if n > 1 { Flow::Break(n - 1) } else { Flow::Normal };
+            true
+        }
+        Flow::Continue(n) if n > 1 => {
+            session_context.flow = Flow::Continue(n - 1);
+            true
+        }
+        Flow::Continue(_) => {
+            session_context.flow = Flow::Normal;
+            false
+        }
+        Flow::Return(_) | Flow::Exit(_) => true,
+        Flow::Normal => false,
+    }
+}
+
+// status of a group is the status of its last command
+fn group_output(res: Vec<(usize,String)>) -> (usize,String){
+    let status = res.last().map(|r| r.0).unwrap_or_default();
+    let output = res.into_iter()
+        .map(|r| r.1)
+        .filter(|r| !r.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (status,output)
+}
+
+
+pub fn run_commands(commands: Commands,session_context: &mut SessionContext) -> (usize,String){
+    try_run_commands(commands, session_context).unwrap_or_else(|e| {
+        error_log(e.to_string());
+        e.into()
+    })
+}
+
+// like run_commands, failures as TiksError
+pub fn try_run_commands(commands: Commands,session_context: &mut SessionContext) -> TiksResult{
+    let (commands,pid,tid,priority) = handle_command(commands);
     let semaphore = semaphore_new();
     let mut tcb = ThreadControlBlock::new();
     let mut pcb = ProcessManager::new();
 
 
-    let (command,_option,arg) = split(commands.clone());
+    let command = commands.command.clone();
 
     add_command_to_thread(tid, command.clone(), priority, &mut tcb);
     add_thread_to_process(pid, command.clone(), tcb.clone(), semaphore, &mut pcb);
@@ -47,124 +377,170 @@ where Vec<String>: From<T>
     pcb.start_process(pid);
 
     
-    match command.as_str(){
-        "ps" => {
-            pcb.kill(pid);
-            tcb.stop_thread(priority_tid);
-            (tid,ps())
-        }
-        "kill" => {
-            tcb.stop_thread(priority_tid);
-            pcb.kill(pid);
-            (tid,pcb.kill(arg[0].parse::<usize>().unwrap()))
-        },
-        "sleep" => {
-            pcb.kill(pid);
-            tcb.stop_thread(priority_tid);
-            (tid,sleep(&mut tcb, commands.arg[0].parse::<usize>().unwrap()))
-        }
-        _ =>{
-            // start process and thread
-            if session_context.user_state.root.check_permission(){
-                // Execute root commands
-                // Handle commands differently when user is in root mode
-                if let Ok(res) = command_match(commands, session_context){
-                    let status = res.0.clone();
-                    let result = res.1.clone();
-                    if status==0{
-                        pcb.kill(pid);
-                        tcb.stop_thread(priority_tid);
-                        (tid,result)
-                    }else{
-                        error_log(res.1.clone());  
-                        (tid,result)
-                    }
-                }else{(tid,String::from("Error: Not found"))}
-            } else if !session_context.user_state.root.check_permission() && !session_context.root.allowed_commands.contains(&commands.command) {
-                // Execute normal commands
-                // Handle commands normally when user is not in root mode
-                if let Ok(res) = command_match(commands, session_context) {
-                    let status = res.0.clone();
-                    let result = res.1.clone();
-                    if status==0{
-                        pcb.kill(pid);
-                        tcb.stop_thread(priority_tid);
-                        (tid,result)
-                    }else{
-                        error_log(res.1.clone());
-                        (tid,result)
-                    }
-                }else {
-                    (tid,String::from("Error: Not found"))
-                }
-            }else{
-                (tid,String::from("Error: Permission not support"))
+    // start process and thread
+    let res = command_match(commands, session_context)?;
+    if res.0==0{
+        pcb.kill(pid);
+        tcb.stop_thread(priority_tid);
+    }else{
+        error_log(res.1.clone());
+    }
+    Ok(res)
+}
+
+
+// a script, `tiks -c` or commands from a pipe: read a command at a time like the prompt,
+// so an alias set on one line works on the next; returns the status of the last command
+pub fn run_script(text: &str, session_context: &mut SessionContext) -> usize{
+    let mut pending = String::new();
+    for line in text.split_inclusive('\n'){
+        pending.push_str(line);
+        // a \ at the end of the line goes on on the next one
+        if pending.ends_with("\\\n"){
+            continue;
+        }
+        let list = match parse_with(&pending, &session_context.aliases){
+            Ok(list) => list,
+            Err(e) if e.incomplete => continue,
+            Err(e) => {
+                eprintln!("{}",e);
+                return MISSING_PARAMETERS;
+            }
+        };
+        pending.clear();
+        for (_status,r) in run_list(&list, session_context){
+            if !r.is_empty(){
+                println!("{r}")
             }
         }
+        // `return` in a sourced file ends it, and so does `exit` in a subshell
+        if let Flow::Return(_) | Flow::Exit(_) = session_context.flow{
+            return session_context.last_status;
+        }
+    }
+    // an unclosed quote or block at the end
+    if let Err(e) = parse_with(&pending, &session_context.aliases){
+        eprintln!("{}",e);
+        return MISSING_PARAMETERS;
+    }
+    session_context.last_status
+}
+
+
+// source FILE [ARG...], `. FILE`: run FILE in this session, so its variables,
+// functions, aliases and `cd` stay; ARG are $1... while it runs
+pub fn source(words: &[String], session_context: &mut SessionContext) -> TiksResult{
+    let Some(file) = words.first() else {
+        return Err(TiksError::usage("source: usage: source FILE [ARG...]"));
+    };
+    if session_context.sourcing >= MAX_CALL_DEPTH{
+        return Err(TiksError::usage(format!("source: {}: maximum nesting level exceeded ({})",file,MAX_CALL_DEPTH)));
+    }
+    let text = fs::read_to_string(file).map_err(|e| TiksError::not_found(format!("source: {}",file)).with_source(e))?;
+
+    // without ARG the file sees the $1... of the caller
+    let saved_args = (words.len() > 1).then(|| std::mem::replace(&mut session_context.args, words[1..].to_vec()));
+    session_context.sourcing += 1;
+    let mut status = run_script(&text, session_context);
+    if let Flow::Return(n) = session_context.flow{
+        status = n;
+        session_context.flow = Flow::Normal;
+    }
+    session_context.sourcing -= 1;
+    if let Some(args) = saved_args{
+        session_context.args = args;
+    }
+    Ok((status,String::new()))
+}
+
+// run at the start of the interactive shell, `tiks --norc` skips it
+pub fn rc_file() -> Option<PathBuf>{
+    dirs::home_dir().map(|home| home.join(".Tiks").join("tiksrc"))
+}
+
+// ~/.Tiks/tiksrc when there is one
+pub fn source_rc(session_context: &mut SessionContext){
+    if let Some(file) = rc_file().filter(|f| f.is_file()){
+        let file = file.to_string_lossy().to_string();
+        if let Err(e) = source(&[file], session_context){
+            eprintln!("{}",e);
+        }
     }
 }
 
 
 use rustyline::Editor;
+use crate::complete::TiksHelper;
 use rustyline::error::ReadlineError;
 
+const CONTINUE_PROMPT: &str = "> ";
+
 // Can't add tid in output
-pub fn init_shell(session_context: &mut SessionContext){
+// returns the last status when input ends
+pub fn init_shell(session_context: &mut SessionContext) -> usize{
     // init an Editor
-    let mut rl = Editor::<()>::new();
+    let mut rl = Editor::<TiksHelper>::new();
+    rl.set_helper(Some(TiksHelper::new()));
     let mut hash = HashMap::new();
     hash.insert(208, ("bash".to_owned(),ProcessState::Running));
     hash.insert(210, ("cmd".to_owned(),ProcessState::Running));
 
     RUNNING_P.lock().unwrap().push(hash);
     loop {
+        // functions and aliases defined by the last line complete too
+        if let Some(helper) = rl.helper_mut(){
+            helper.functions = session_context.functions.keys().cloned().collect();
+            helper.aliases = session_context.aliases.keys().cloned().collect();
+        }
         let readline: Result<String, ReadlineError> = rl.readline(&print_prompt(session_context));
         match readline {
             Ok(line) => {
+                // rustyline keeps the `\n` when stdin is not a terminal
+                let mut line = line.trim_end_matches(['\n','\r']).to_string();
                 if line.trim().is_empty() {
                     continue;
                 }
+                // quotes, here-documents, `|` or `&&` at the end: read more lines
+                let mut parsed = parse_with(&line, &session_context.aliases);
+                while matches!(&parsed, Err(e) if e.incomplete){
+                    match rl.readline(CONTINUE_PROMPT){
+                        Ok(next) => {
+                            line.push('\n');
+                            line.push_str(next.trim_end_matches(['\n','\r']));
+                            parsed = parse_with(&line, &session_context.aliases);
+                        }
+                        Err(_) => break,
+                    }
+                }
                 // add Key::UP and Key::DOWN to find history
                 rl.add_history_entry(line.clone());
                 // add line in lazy HISTORY
                 history_push(line.clone());
                 
-                if line.parse::<usize>().is_ok() {
-                    let (_i, res) = get_last(line.parse::<usize>().unwrap());
-                    match res {
+                let line = match line.parse::<usize>(){
+                    Ok(index) => match get_last(index).1{
                         Some(command) => {
-                            let args: Vec<String> = command.split_whitespace().map(|s| s.to_string()).collect();
-                            run(args, session_context);
-                        }
-                        None => {
-                            continue;
+                            parsed = parse_with(&command, &session_context.aliases);
+                            command
                         }
-                    }
-                } else {
-                    let args: Box<Vec<String>> = Box::new(line.split_whitespace().map(|s| s.to_string()).collect());
-                    if args.contains(&"&&".to_string()) {
-                        let res = and(*args, session_context);
-                        for (t,r) in res{
-                            println!("Done[{t}]: \n{r}")
-                        }
-                    } else if args.contains(&"|".to_string()) {
-                        let res = pipe(*args).unwrap();
-                        println!("{}",res.1)
-                    } else if args.contains(&"&".to_string()) {
-                        let res = priority_run(*args, session_context);
-                        for (t,r) in res{
-                            println!("Done[{t}]: \n{r}")
-
+                        None => continue,
+                    },
+                    Err(_) => line,
+                };
+                match parsed{
+                    Ok(list) => {
+                        for (_status,r) in run_list(&list, session_context){
+                            if !r.is_empty(){
+                                println!("{r}")
+                            }
                         }
-                    } else {
-                        let (t,res) = run(*args, session_context);
-                        println!("Done[{t}]: \n{res}")
                     }
-                    
+                    Err(e) => eprintln!("{}",e.pointer(&line)),
                 }
             }
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
+                session_context.last_status = INTERRUPTED;
                 break;
             }
             Err(ReadlineError::Eof) => {
@@ -172,11 +548,12 @@ pub fn init_shell(session_context: &mut SessionContext){
                 break;
             }
             Err(err) => {
-                println!("Error: {:?}", err);
+                eprintln!("Error: {:?}", err);
                 break;
             }
         }
     }
+    session_context.last_status
 }
 
 // root
@@ -186,6 +563,8 @@ fn print_prompt(session_context: &mut SessionContext) -> String{
         whoami="root".to_string()
     }
     let pwd = pwd().unwrap().1;
-    let input = format!("\x1B[32;1m{}\x1B[0m:\x1B[34m{}>>\x1B[0m ",whoami,pwd); // Assuming whoami() returns the current user
+    // colors from [prompt] in ~/.Tiks/config.toml
+    let colors = crate::config::current().prompt;
+    let input = format!("\x1B[{}m{}\x1B[0m:\x1B[{}m{}>>\x1B[0m ",colors.user,whoami,colors.path,pwd); // Assuming whoami() returns the current user
     input
 }
\ No newline at end of file
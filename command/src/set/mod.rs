@@ -1,20 +1,22 @@
+#[allow(clippy::module_inception)]
 pub mod set;
 
 
 pub mod version{
-    use std::io::{self, Read};
+    use std::io::Read;
 
     use crate::start::state_code::STATUE_CODE;
+    use crate::start::error::TiksResult;
 
-    pub fn get_version_list() -> io::Result<(usize,String)>{
+    pub fn get_version_list() -> TiksResult{
         let mut s = String::new();
-        let mut res = std::fs::File::open("command/test/version").unwrap();
-        let _ = res.read_to_string(&mut s);
+        let mut res = std::fs::File::open("command/test/version")?;
+        res.read_to_string(&mut s)?;
 
         Ok((STATUE_CODE,s))
     }
 
-    pub fn get_version_now() -> io::Result<(usize,String)>{
+    pub fn get_version_now() -> TiksResult{
         let s = env!("CARGO_PKG_VERSION").parse::<String>().unwrap();
         Ok((STATUE_CODE,s))
     }
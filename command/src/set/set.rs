@@ -1,3 +1,6 @@
+use crate::commands::registry;
+use crate::root::Functions;
+use crate::commands::alias::Aliases;
 use crate::commands::command::{get_time, HISTROY};
 use chrono::{DateTime, Datelike, Local, Timelike};
 
@@ -5,7 +8,7 @@ use std::{fs::OpenOptions, io::Write, path::Path, time::Duration};
 
 pub fn get_last(index: usize) -> (usize,Option<String>){
     let len = HISTROY.lock().unwrap().len();
-    if index > len{
+    if index >= len{
         return (1,None);
     }
     let res = &HISTROY.lock().unwrap()[index];
@@ -41,7 +44,7 @@ fn turn_time(du: Duration) -> String{
 
 pub fn file_create_time(path: &str) -> String{
     let mut time = String::new();
-    if let Ok(metadata) = std::fs::metadata(&path) {
+    if let Ok(metadata) = std::fs::metadata(path) {
         if let Ok(created) = metadata.created() {
             let du = created.elapsed().unwrap();
             time = turn_time(du);
@@ -51,14 +54,13 @@ pub fn file_create_time(path: &str) -> String{
 }
 
 
-// get similar command
-pub fn get_similar(arg: &str) -> Vec<String>{
-    let commands = vec!["ls","pwd","pd","history","whoami","help","ll","cd","mv","cp","rn","tar","rm","mkdir","touch","python","html","web","cat","exit","sudo","apt","ps","sleep","kill"];
+// get similar command, builtins, functions and aliases
+pub fn get_similar(arg: &str, functions: &Functions, aliases: &Aliases) -> Vec<String>{
     let mut output = Vec::new();
-    let threshold = 1;
-    for command in commands {
-        if levenshtein_distance(arg, command) <= threshold{
-            output.push(command.to_string())
+    let threshold = crate::config::current().suggest.threshold;
+    for command in registry::names().into_iter().chain(functions.keys().cloned()).chain(aliases.keys().cloned()) {
+        if levenshtein_distance(arg, &command) <= threshold{
+            output.push(command)
         }
     }
     output
@@ -70,12 +72,12 @@ fn levenshtein_distance(arg:&str,command:&str) -> usize{
 
     let mut dp = vec![vec![0;len2+1];len1+1];
 
-    for i in 0..=len1 {
-        dp[i][0] = i;
+    for (i, row) in dp.iter_mut().enumerate() {
+        row[0] = i;
     }
 
-    for j in 0..=len2 {
-        dp[0][j] = j;
+    for (j, cell) in dp[0].iter_mut().enumerate() {
+        *cell = j;
     }
 
     for (i,c) in arg.chars().enumerate(){
@@ -96,12 +98,9 @@ fn levenshtein_distance(arg:&str,command:&str) -> usize{
 }
 
 
-// write error.log
+// write error.log, log.error_log in ~/.Tiks/config.toml
 fn get_home_err() -> String{
-    let home_dir = dirs::home_dir().expect("Failed to get home directory");
-    let binding = home_dir.join(".Tiks").join("error.log");
-    let res = binding.as_os_str().to_str().unwrap();
-    res.to_string()
+    crate::config::current().error_log().to_string_lossy().to_string()
 }
 
 pub fn error_log(err: String){
@@ -131,7 +130,6 @@ pub fn error_log(err: String){
 
     if let Err(e) = file.write_all(write_err) {
         eprintln!("Failed to write to file: {}", e);
-        return;
     }
     // error.log -> $HOME/.Tiks/error.log -> [time]: [Error]
 }
@@ -34,6 +34,7 @@ impl Semaphore{
     }
 }
 
+// run.semaphore in ~/.Tiks/config.toml
 pub fn semaphore_new() -> Semaphore{
-    Semaphore::new(2)
+    Semaphore::new(crate::config::current().run.semaphore)
 }
\ No newline at end of file
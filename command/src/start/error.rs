@@ -0,0 +1,149 @@
+// failure of a command
+// the code is the Tiks status (`$?`), exit_code() in state_code.rs maps it
+// for the process; Display is what the terminal shows:
+//
+//  Error: copy a to b failed: No such file or directory (os error 2)
+//         ^ message             ^ source chain
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use super::state_code::{IO_ERROR, MISSING_PARAMETERS, NETWORK_ERROR, NOT_EXIST, PERMISSION};
+
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Category {
+    Usage,
+    NotFound,
+    Permission,
+    Io,
+    Network,
+}
+
+impl Category {
+    // status of a category when no other code is given
+    pub fn code(self) -> usize {
+        match self {
+            Category::Usage => MISSING_PARAMETERS,
+            Category::NotFound => NOT_EXIST,
+            Category::Permission => PERMISSION,
+            Category::Io => IO_ERROR,
+            Category::Network => NETWORK_ERROR,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Usage => "usage",
+            Category::NotFound => "not-found",
+            Category::Permission => "permission",
+            Category::Io => "io",
+            Category::Network => "network",
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct TiksError {
+    code: usize,
+    category: Category,
+    message: String,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+// Ok keeps a status too: programs from PATH exit with their own code
+pub type TiksResult = Result<(usize,String),TiksError>;
+
+impl TiksError {
+    pub fn new<T: Into<String>>(category: Category, message: T) -> TiksError {
+        TiksError {
+            code: category.code(),
+            category,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn usage<T: Into<String>>(message: T) -> TiksError {
+        TiksError::new(Category::Usage, message)
+    }
+
+    pub fn not_found<T: Into<String>>(message: T) -> TiksError {
+        TiksError::new(Category::NotFound, message)
+    }
+
+    pub fn permission<T: Into<String>>(message: T) -> TiksError {
+        TiksError::new(Category::Permission, message)
+    }
+
+    pub fn io<T: Into<String>>(message: T) -> TiksError {
+        TiksError::new(Category::Io, message)
+    }
+
+    pub fn network<T: Into<String>>(message: T) -> TiksError {
+        TiksError::new(Category::Network, message)
+    }
+
+    pub fn with_code(mut self, code: usize) -> TiksError {
+        self.code = code;
+        self
+    }
+
+    pub fn with_source<E: Into<Box<dyn Error + Send + Sync>>>(mut self, source: E) -> TiksError {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn code(&self) -> usize {
+        self.code
+    }
+
+    pub fn category(&self) -> Category {
+        self.category
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for TiksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.message.is_empty() {
+            parts.push(self.message.clone());
+        }
+        let mut source = self.source();
+        while let Some(e) = source {
+            parts.push(e.to_string());
+            source = e.source();
+        }
+        write!(f, "Error: {}", parts.join(": "))
+    }
+}
+
+impl Error for TiksError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+    }
+}
+
+// `?` on io: the io::Error is the source, the kind picks the category
+impl From<io::Error> for TiksError {
+    fn from(e: io::Error) -> TiksError {
+        let category = match e.kind() {
+            io::ErrorKind::NotFound => Category::NotFound,
+            io::ErrorKind::PermissionDenied => Category::Permission,
+            _ => Category::Io,
+        };
+        TiksError::new(category, "").with_source(e)
+    }
+}
+
+// status and text for the terminal
+impl From<TiksError> for (usize,String) {
+    fn from(e: TiksError) -> (usize,String) {
+        (e.code(), e.to_string())
+    }
+}
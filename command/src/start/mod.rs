@@ -1,2 +1,3 @@
 pub mod start_logo;
-pub mod state_code;
\ No newline at end of file
+pub mod state_code;
+pub mod error;
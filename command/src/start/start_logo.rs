@@ -46,39 +46,33 @@ pub fn start_logo() {
     // logo
     println!("{:width$}", "", width = padding);
     println!(
-        "{}{}{}",
+        "{}  _______ _ _{}",
         color::Fg(color::Red),
-        "  _______ _ _",
         style::Reset
     );
     println!(
-        "{}{}{}",
+        "{} |__   __(_) |{}",
         color::Fg(color::Blue),
-        " |__   __(_) |",
         style::Reset
     );
     println!(
-        "{}{}{}",
+        "{}    | |   _| |_ ___{}",
         color::Fg(color::Green),
-        "    | |   _| |_ ___",
         style::Reset
     );
     println!(
-        "{}{}{}",
+        "{}    | |  | | __/ __|{}",
         color::Fg(color::Yellow),
-        "    | |  | | __/ __|",
         style::Reset
     );
     println!(
-        "{}{}{}",
+        "{}    | |  | | |\\__ \\{}",
         color::Fg(color::Cyan),
-        "    | |  | | |\\__ \\",
         style::Reset
     );
     println!(
-        "{}{}{}",
+        "{}    |_|  |_|\\__|___/{}",
         color::Fg(color::Magenta),
-        "    |_|  |_|\\__|___/",
         style::Reset
     );
     println!("{:width$}", "", width = padding);
@@ -86,15 +80,13 @@ pub fn start_logo() {
 
     // github email
     println!(
-        "{}{}{}",
+        "{} * Contact us :  zzj01262022@163.com{}",
         color::Fg(color::Reset),
-        " * Contact us :  zzj01262022@163.com",
         style::Reset
     );
     println!(
-        "{}{}{}",
+        "{} * Github :  https://github.com/zhangzijie-pro/Tiks/{}",
         color::Fg(color::Reset),
-        " * Github :  https://github.com/zhangzijie-pro/Tiks/",
         style::Reset
     );
     println!();
@@ -104,7 +96,7 @@ pub fn start_logo() {
     println!(
         "{}Current Date & Time: {}",
         style::Italic,
-        now.format("%Y-%m-%d %H:%M:%S").to_string()
+        now.format("%Y-%m-%d %H:%M:%S")
     );
     println!();
 
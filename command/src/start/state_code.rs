@@ -1,33 +1,119 @@
+use super::error::TiksError;
+
 pub const STATUE_CODE:usize=0;
+pub const NOT_IN_PATH:usize=1;
+pub const FALSE_STATUS:usize=1;  // a plain false: `let 0`, `expr 0`
+pub const CODE_ERROR:usize=103;
+pub const MISSING_PARAMETERS:usize=104;
+pub const NOT_EXIST:usize=105;
+pub const IO_ERROR:usize=106;
+pub const NETWORK_ERROR:usize=107;
+pub const ENV_ERROR:usize=108;
+pub const PIPE_ERROR:usize=110;
+pub const INTERRUPTED:usize=130;
+pub const PERMISSION:usize=401;
+pub const UNKNOWN_COMMAND:usize=403;
+pub const NOT_FOUND:usize=404;
+pub const APT_HINT:usize=408;
+// a status from a program, `exit N` or `return N` that would read as one of the codes above
+pub const RAW_STATUS:usize=1<<16;
+
+// Tiks status -> exit code (0-255)
+// `$?` keeps the Tiks status, the process exits with the mapped code
+// TiksError::category() gives the default status of a failure
+//
+//  status  meaning                         category     exit code
+//  0       success                                      0
+//  1       name not found (which, type)    not-found    1
+//  103     code error (python, html)       io           1
+//  104     missing parameters, usage       usage        2
+//  105     file or dir is not exist        not-found    1
+//  106     read or write failed            io           1
+//  107     download failed                 network      1
+//  108     can't set env                   io           1
+//  110     meaningless pipe                io           2
+//  401     permission not support          permission   126
+//  403     unknown command                 not-found    127
+//  404     not found                       not-found    127
+//  408     command not found (apt hint)    not-found    127
+//  other   < 256 as is, otherwise                       1
+//  RAW_STATUS + N, see raw_status()                     N
+pub fn exit_code(status: usize) -> i32 {
+    match status {
+        RAW_STATUS.. => (status - RAW_STATUS) as i32 & 0xff,
+        CODE_ERROR | NOT_EXIST | IO_ERROR | NETWORK_ERROR | ENV_ERROR => 1,
+        MISSING_PARAMETERS | PIPE_ERROR => 2,
+        PERMISSION => 126,
+        UNKNOWN_COMMAND | NOT_FOUND | APT_HINT => 127,
+        0..=255 => status as i32,
+        _ => 1,
+    }
+}
+
+// exit code N of a program as a status: `sh -c "exit 104"` exits 104, not 2
+pub fn raw_status(code: usize) -> usize {
+    if exit_code(code) == code as i32 { code } else { RAW_STATUS + code }
+}
+
+// the number `$?` shows
+pub fn status_value(status: usize) -> usize {
+    status.checked_sub(RAW_STATUS).unwrap_or(status)
+}
+
+pub fn missing_pattern() -> TiksError {
+    TiksError::usage("Missing parameters")
+}
 
-pub fn missing_pattern() -> (usize, String) {
-    (104, "Error: Missing parameters".to_string())
+// wrong flags or arguments, with the usage of the command
+pub fn usage_error(msg: &str) -> TiksError {
+    TiksError::usage(msg)
 }
 
 pub fn env() -> (usize, String) {
     (STATUE_CODE, "Env set over".to_string())
 }
 
-pub fn run_code_er() -> (usize, String) {
-    (103, "Error: code error".to_string())
+pub fn env_err(key: &str) -> TiksError {
+    TiksError::io(format!("Can't set env {}", key)).with_code(ENV_ERROR)
+}
+
+pub fn run_code_er() -> TiksError {
+    TiksError::io("code error").with_code(CODE_ERROR)
 }
 
 pub fn run_code() -> (usize, String) {
     (STATUE_CODE, "Running...".to_string())
 }
 
-pub fn empty_file() -> (usize, String) {
-    (105, "Error: File is not exist".to_string())
+pub fn empty_file() -> TiksError {
+    TiksError::not_found("File is not exist")
 }
 
-pub fn empty_dir() -> (usize, String) {
-    (105, "Error: Dir is not exist".to_string())
+pub fn empty_dir() -> TiksError {
+    TiksError::not_found("Dir is not exist")
 }
 
-pub fn pipe_err() -> (usize,String){
-    (110,"Error: Meaningless".to_string())
+pub fn pipe_err() -> TiksError {
+    TiksError::io("Meaningless").with_code(PIPE_ERROR)
 }
 
-pub fn not_found() -> (usize,String){
-    (404,"Error: Not found this command in terimal".to_string())
-}
\ No newline at end of file
+pub fn permission_denied() -> TiksError {
+    TiksError::permission("Permission not support")
+}
+
+pub fn not_in_path(name: &str) -> TiksError {
+    TiksError::not_found(format!("{}: not found", name)).with_code(NOT_IN_PATH)
+}
+
+// the `Did you mean?` list only when there is something similar
+pub fn unknown_command(name: &str, similar: &str) -> TiksError {
+    let mut msg = format!("Can't found this \x1B[31m{}\x1B[0m", name);
+    if !similar.trim().is_empty() {
+        msg.push_str(&format!("\n    Did you mean?\n{}", similar));
+    }
+    TiksError::not_found(msg).with_code(UNKNOWN_COMMAND)
+}
+
+pub fn not_found() -> TiksError {
+    TiksError::not_found("Not found this command in terimal").with_code(NOT_FOUND)
+}
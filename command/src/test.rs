@@ -1,20 +1,605 @@
 #[cfg(test)]
 mod tests {
     use crate::{commands::command::echo_print, env::init_env};
+    use crate::parse::lexer::{tokenize, Op};
+    use crate::parse::ast::{CommandKind, Connector};
+    use crate::parse::parser::{parse, parse_with};
+    use crate::commands::alias::{self, Aliases};
+    use crate::start::state_code::{exit_code, raw_status, status_value, unknown_command, FALSE_STATUS, PERMISSION};
+    use crate::start::error::{Category, TiksError, TiksResult};
+    use crate::commands::command::grep_lines;
+    use crate::env::find_in_path;
+    use crate::commands::command::{command_v, export, mkdir, rename, rm, type_of, which};
+    use crate::commands::registry;
+    use crate::priority::{get_priority, CommandPriority};
+    use crate::set::set::get_similar;
+    use crate::commands::spec::{Args, Spec};
+    use crate::commands::condition;
+    use crate::expand::{arith, brace, expand_fields, expand_word, glob, pattern};
+    use crate::commands::expr::expr;
+    use crate::root::{SessionContext, User, UserRole};
+    use crate::root::Functions;
+    use crate::complete::TiksHelper;
+    use crate::redirect::{self, Buffer, Output, Streams};
+    use crate::run::{run_list, run_script, source, Flow};
+    use crate::config::{self, Config};
+    use std::io::{self, Cursor, Read};
+    use std::path::PathBuf;
+
+    // a non-root session (root only after sudo) that does not read ~/.Tiks
+    fn session() -> SessionContext {
+        SessionContext::with_user(User::new("tester".to_string(), String::new(), true))
+    }
+
+    // a file or directory in the temp dir, one for each test and process
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tiks_{}_{}", name, std::process::id()))
+    }
 
     #[test]
-    #[ignore]
     fn detection_func() {
+        // $YOUR_env is expanded before echo, echo prints it as it is
         let input = "$YOUR_env";
         let (ouput_n,output_s) = echo_print(input.to_string());
         assert_eq!(0,ouput_n);      // passed
-        assert_eq!("your_env_value",output_s)  // value
+        assert_eq!("$YOUR_env",output_s)
+    }
+
+    #[test]
+    fn expand_params() {
+        let mut session = session();
+        let mut expand = |line: &str| {
+            let tokens = tokenize(line).unwrap();
+            let word = tokens[0].as_word().unwrap().clone();
+            expand_word(&word, &mut session).map_err(|e| e.to_string())
+        };
+        std::env::set_var("TIKS_TEST_PATH", "/a/b/c.tar.gz");
+        std::env::remove_var("TIKS_TEST_UNSET");
+        assert_eq!(Ok("[]".to_string()), expand("[$TIKS_TEST_UNSET]"));
+        assert_eq!(Ok("x y".to_string()), expand(r#"${TIKS_TEST_UNSET:-"x y"}"#));
+        assert_eq!(Ok("alt".to_string()), expand("${TIKS_TEST_PATH:+alt}"));
+        assert_eq!(Ok("13".to_string()), expand("${#TIKS_TEST_PATH}"));
+        assert_eq!(Ok("c.tar.gz /a/b/c".to_string()), expand(r#""${TIKS_TEST_PATH##*/} ${TIKS_TEST_PATH%%.*}""#));
+        assert_eq!(Err("Error: TIKS_TEST_UNSET: no".to_string()), expand("${TIKS_TEST_UNSET?no}"));
+        assert_eq!(Ok("~".to_string()), expand("'~'"));
+        assert!(tokenize("echo ${TIKS").unwrap_err().incomplete);
+        assert!(tokenize("echo ${!x}").is_err());
+
+        session.options.nounset = true;
+        let tokens = tokenize("$TIKS_TEST_UNSET").unwrap();
+        let err = expand_word(tokens[0].as_word().unwrap(), &mut session).unwrap_err();
+        assert_eq!("Error: TIKS_TEST_UNSET: unbound variable", err.to_string());
     }
 
     #[test]
     fn env_set() {
-        let s = init_env();         // set env in your os test
-        assert_eq!((),s);
+        init_env();         // set env in your os test
+    }
+
+    #[test]
+    fn tokenize_quotes() {
+        let tokens = tokenize(r#"touch "my file.txt" 'a  b' c\ d # comment"#).unwrap();
+        let words: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+        assert_eq!(vec!["touch", "my file.txt", "a  b", "c d"], words);
+        assert!(tokenize("echo \"oops").is_err());
+    }
+
+    #[test]
+    fn tokenize_quoted_operator() {
+        let tokens = tokenize("echo '|' \"&&\" | grep a&&pwd").unwrap();
+        assert!(tokens[1].as_word().is_some());
+        assert!(tokens[2].as_word().is_some());
+        assert!(tokens[3].is_op(Op::Pipe));
+        assert!(tokens[6].is_op(Op::And));
+    }
+
+    #[test]
+    fn and_or_status() {
+        let mut session = session();
+        let status = run_script("false && A=1 || B=1; true || C=1\ntrue && false || D=1; false", &mut session);
+        assert_eq!(None, session.get_var("A"));
+        assert_eq!(Some("1".to_string()), session.get_var("B"));
+        assert_eq!(None, session.get_var("C"));
+        assert_eq!(Some("1".to_string()), session.get_var("D"));
+        // the status of the last command
+        assert_eq!(1, exit_code(status));
+    }
+
+    #[test]
+    fn exit_in_subshell() {
+        let mut session = session();
+        run_script("x=$(exit 3); A=$?\n(exit 4); B=$?\necho a | exit 5; C=$?\n(f() { exit 6; }; f; X=1); D=$?", &mut session);
+        let get = |name: &str| session.get_var(name).unwrap_or_default();
+        assert_eq!(("3", "4", "5", "6"), (get("A").as_str(), get("B").as_str(), get("C").as_str(), get("D").as_str()));
+        assert_eq!(None, session.get_var("X"));
+        assert_eq!((Flow::Normal, 0), (session.flow, session.subshell));
+    }
+
+    #[test]
+    fn subshell_keeps_its_changes() {
+        let mut session = session();
+        run_script("X=0; (X=1; Y=1; f() { :; }; set -u; export TIKS_SUBSHELL=3; sh -c 'exit $TIKS_SUBSHELL'); S=$?", &mut session);
+        assert_eq!(Some("0".to_string()), session.get_var("X"));
+        assert_eq!((None, None), (session.get_var("Y"), std::env::var("TIKS_SUBSHELL").ok()));
+        assert!(!session.functions.contains_key("f") && !session.options.nounset);
+        // programs started inside see the exports of the ( )
+        assert_eq!(Some("3".to_string()), session.get_var("S"));
+    }
+
+    #[test]
+    fn pipeline_stage_keeps_cd_and_export() {
+        let mut session = session();
+        let cwd = std::env::current_dir().unwrap();
+        run_script("{ cd /; export TIKS_STAGE=7; sh -c 'echo $TIKS_STAGE'; } | sh -c 'read x; exit $x'; S=$?", &mut session);
+        assert_eq!(cwd, std::env::current_dir().unwrap());
+        assert_eq!(None, std::env::var("TIKS_STAGE").ok());
+        // the next stage got what the programs of the first one printed
+        assert_eq!(Some("7".to_string()), session.get_var("S"));
+    }
+
+    #[test]
+    fn parse_mixed_operators() {
+        let list = parse("ls | grep a && pwd > out; { cd src; ls; } &").unwrap();
+        assert_eq!(2, list.items.len());
+        let first = &list.items[0].and_or;
+        assert_eq!(2, first.first.commands.len());
+        assert_eq!(Connector::And, first.rest[0].0);
+        assert_eq!("out", first.rest[0].1.commands[0].redirects[0].target.text());
+        assert!(list.items[1].background);
+        assert!(matches!(list.items[1].and_or.first.commands[0].kind, CommandKind::Group(_)));
+    }
+
+    #[test]
+    fn parse_error_column() {
+        assert_eq!(6, parse("ls | | pwd").unwrap_err().col);
+        assert_eq!(6, parse("ls &&").unwrap_err().col);
+        assert!(parse("{ ls }").is_err());
+    }
+
+    #[test]
+    fn and_or_skip() {
+        let mut session = session();
+        // which commands ran and whether each succeeded
+        let mut ran = |line: &str| -> Vec<bool> {
+            run_list(&parse(line).unwrap(), &mut session).iter().map(|r| r.0 == 0).collect()
+        };
+        assert_eq!(vec![false, true], ran("no_such_cmd && pwd || pwd"));
+        assert_eq!(vec![true, true], ran("pwd || no_such_cmd && pwd"));
+        assert_eq!(vec![false, true], ran("no_such_cmd; pwd"));
+    }
+
+    #[test]
+    fn parse_script() {
+        // the shebang is a comment, a line ending with | goes on
+        let list = parse("#!/usr/bin/env tiks\necho one\n\nls |\n  grep a\n").unwrap();
+        assert_eq!(2, list.items.len());
+        assert_eq!(2, list.items[1].and_or.first.commands.len());
+    }
+
+    #[test]
+    fn parse_compound() {
+        let list = parse("if a; then b; elif c; then d; else e; fi\nfor x in 1 2; do f; done > out").unwrap();
+        match &list.items[0].and_or.first.commands[0].kind {
+            CommandKind::If(clause) => assert_eq!((2, true), (clause.branches.len(), clause.otherwise.is_some())),
+            kind => panic!("{:?}", kind),
+        }
+        let command = &list.items[1].and_or.first.commands[0];
+        assert!(matches!(&command.kind, CommandKind::For(f) if f.name == "x" && f.words.as_ref().unwrap().len() == 2));
+        assert_eq!(1, command.redirects.len());
+
+        let list = parse("case $x in a|b) one;; (*) two\nesac").unwrap();
+        match &list.items[0].and_or.first.commands[0].kind {
+            CommandKind::Case(case) => assert_eq!(vec![2, 1], case.items.iter().map(|i| i.patterns.len()).collect::<Vec<_>>()),
+            kind => panic!("{:?}", kind),
+        }
+        // `fi` as an argument, unfinished clauses ask for more lines
+        assert!(parse("echo fi done").is_ok());
+        assert!(parse("while true; do ls").unwrap_err().incomplete);
+        assert!(!parse("if then fi").unwrap_err().incomplete);
+    }
+
+    #[test]
+    fn functions() {
+        let list = parse("greet() {\n  echo hi $1\n}\nfunction two ( ls )").unwrap();
+        let mut functions = Functions::new();
+        for item in &list.items {
+            match &item.and_or.first.commands[0].kind {
+                CommandKind::Function(f) => functions.insert(f.name.clone(), (*f.body).clone()),
+                kind => panic!("{:?}", kind),
+            };
+        }
+        assert!(parse("f() echo").is_err());
+        assert_eq!("greet is a function", type_of(&["greet".to_string()], &functions).unwrap().1);
+        assert!(get_similar("gret", &functions, &Aliases::new()).contains(&"greet".to_string()));
+
+        let mut helper = TiksHelper::new();
+        helper.functions = functions.keys().cloned().collect();
+        let names = helper.commands("gre");
+        assert!(names.contains(&"greet".to_string()) && names.contains(&"grep".to_string()));
+    }
+
+    #[test]
+    fn command_substitution() {
+        let mut session = session();
+        let mut fields = |line: &str| {
+            let tokens = tokenize(line).unwrap();
+            expand_fields(tokens[0].as_word().unwrap(), &mut session).unwrap()
+        };
+        // split unless quoted, trailing newlines are gone
+        assert_eq!(vec!["a", "bc d"], fields(r#"$(echo a b)"c d""#));
+        assert_eq!(vec!["a  b"], fields(r#""$(echo 'a  b')""#));
+        assert_eq!(vec!["in out"], fields(r#""$(echo $(echo in) `echo \`echo out\``)""#));
+        assert!(fields("$(echo)").is_empty());
+        assert!(tokenize("echo $(echo a").unwrap_err().incomplete);
+        assert!(tokenize("echo `echo a").unwrap_err().incomplete);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut session = session();
+        assert_eq!(7, arith::eval("1 + 2 * 3", &mut session).unwrap());
+        assert_eq!(512, arith::eval("2 ** 3 ** 2", &mut session).unwrap());
+        assert_eq!(1, arith::eval("(0x10 == 16) && !(3 > 4)", &mut session).unwrap());
+        assert_eq!(12, arith::eval("n = 5, n += 7", &mut session).unwrap());
+        assert_eq!(Some("12".to_string()), session.get_var("n"));
+        // the side not taken is not run
+        assert_eq!(0, arith::eval("0 && 1 / 0", &mut session).unwrap());
+        let err = arith::eval("1 / (n - 12)", &mut session).unwrap_err();
+        assert_eq!("Error: 1 / (n - 12): division by 0", err.to_string());
+
+        let words = |s: &str| s.split(' ').map(|w| w.to_string()).collect::<Vec<_>>();
+        assert_eq!((0, "9".to_string()), expr(&words("( 1 + 2 ) * 3")).unwrap());
+        assert!(expr(&words("1 / 0")).is_err());
+        // a 0 is false, not a lookup failure
+        assert_eq!((FALSE_STATUS, FALSE_STATUS), (run_script("let 0", &mut session), run_script("let 1 0", &mut session)));
+    }
+
+    #[test]
+    fn brace_expansion() {
+        let braces = |line: &str| -> Vec<String> {
+            let tokens = tokenize(line).unwrap();
+            brace::expand(tokens[0].as_word().unwrap()).iter().map(|w| w.text()).collect()
+        };
+        assert_eq!(vec!["src/bin", "src/lib", "src/tests"], braces("src/{bin,lib,tests}"));
+        assert_eq!(vec!["day08.md", "day09.md", "day10.md"], braces("day{08..10}.md"));
+        assert_eq!(vec!["9", "6", "3", "0"], braces("{9..0..3}"));
+        assert_eq!(vec!["-9223372036854775808", "-9223372036854775807"], braces("{-9223372036854775808..-9223372036854775807}"));
+        assert_eq!(vec!["a", "c", "e"], braces("{a..e..2}"));
+        assert_eq!(vec!["a", "b1", "b2"], braces("{a,b{1..2}}"));
+        // quoted braces, one word and empty braces stay
+        assert_eq!(vec!["{a,b}"], braces("'{a,b}'"));
+        assert_eq!(vec!["{a}x{}"], braces("{a}x{}"));
+    }
+
+    #[test]
+    fn glob_files() {
+        let dir = temp_path("glob");
+        std::fs::create_dir_all(dir.join("src/a")).unwrap();
+        for file in ["b.log", "a.log", ".c.log", "src/m.rs", "src/a/n.rs"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        let dir = dir.to_str().unwrap().to_string();
+        let names = |pattern: &str, dotglob: bool| -> Vec<String> {
+            glob::glob(&format!("{}/{}", dir, pattern), dotglob).iter().map(|p| p[dir.len() + 1..].to_string()).collect()
+        };
+        assert_eq!(vec!["a.log", "b.log"], names("*.log", false));
+        assert_eq!(vec![".c.log", "a.log", "b.log"], names("*.log", true));
+        assert_eq!(vec!["b.log"], names("[!a].l?g", false));
+        assert_eq!(vec!["src/a/n.rs", "src/m.rs"], names("src/**/*.rs", false));
+        assert!(names("*.none", false).is_empty());
+        // quoted parts are escaped, so they never match as patterns
+        assert!(!glob::has_magic(&pattern::escape("*.log")));
+        assert_eq!("*.log", glob::unescape(&pattern::escape("*.log")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shell_variables() {
+        let list = parse("FOO=a:b BAR=~/x echo $FOO").unwrap();
+        let command = &list.items[0].and_or.first.commands[0];
+        let CommandKind::Simple(simple) = &command.kind else { panic!("not a simple command") };
+        assert_eq!(vec!["FOO", "BAR"], simple.assigns.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>());
+        assert_eq!("echo", simple.name());
+
+        // a shell variable is not in the environment until export
+        let mut session = session();
+        session.set_var("TIKS_TEST_VAR", "/a:/b");
+        assert_eq!(None, std::env::var_os("TIKS_TEST_VAR"));
+        assert_eq!(Some("/a:/b".to_string()), session.get_var("TIKS_TEST_VAR"));
+        export(&["TIKS_TEST_VAR".to_string()], &mut session).unwrap();
+        assert_eq!(Ok("/a:/b".to_string()), std::env::var("TIKS_TEST_VAR"));
+        session.unset_var("TIKS_TEST_VAR");
+        assert_eq!(None, session.get_var("TIKS_TEST_VAR"));
+    }
+
+    #[test]
+    fn pattern_and_test() {
+        assert!(pattern::matches("h?llo.t[a-z]t", "hello.txt"));
+        assert!(pattern::matches("*.rs", "main.rs") && !pattern::matches("*.rs", "main.rsx"));
+        assert!(pattern::matches("[!a]*", "ba") && !pattern::matches("[!a]*", "ab"));
+        assert!(pattern::matches(&pattern::escape("*"), "*") && !pattern::matches(&pattern::escape("*"), "x"));
+
+        let test = |expr: &str| condition::test(&expr.split(' ').map(String::from).collect::<Vec<_>>()).map(|r| r.0);
+        assert_eq!(0, test("3 -lt 10").unwrap());
+        assert_eq!(1, test("b < a").unwrap());
+        assert_eq!(0, test("! -z x -a ( a = a -o 1 -eq 2 )").unwrap());
+        assert_eq!(0, test("-d /").unwrap());
+        assert!(test("1 -eq x").is_err());
+    }
+
+    #[test]
+    fn parse_redirects() {
+        let list = parse("python x.py 2> err.log >> out 2>&1 < in &> all").unwrap();
+        let redirects = &list.items[0].and_or.first.commands[0].redirects;
+        let got: Vec<(usize, Op, String)> = redirects.iter().map(|r| (r.fd, r.op, r.target.text())).collect();
+        assert_eq!(vec![
+            (2, Op::Great, "err.log".to_string()),
+            (1, Op::DGreat, "out".to_string()),
+            (2, Op::GreatAnd, "1".to_string()),
+            (0, Op::Less, "in".to_string()),
+            (1, Op::AndGreat, "all".to_string()),
+        ], got);
+        // quoted number is an argument
+        let list = parse("echo '2'>x").unwrap();
+        assert_eq!(1, list.items[0].and_or.first.commands[0].redirects[0].fd);
+    }
+
+    #[test]
+    fn parse_heredoc() {
+        let list = parse("grep hi <<EOF\nhi $?\nEOF").unwrap();
+        let r = &list.items[0].and_or.first.commands[0].redirects[0];
+        assert_eq!(Op::DLess, r.op);
+        assert_eq!("hi $?\n", r.target.text());
+        // body not closed yet: ask for more lines
+        assert!(parse("grep hi <<EOF").unwrap_err().incomplete);
+        assert!(parse("echo 'a").unwrap_err().incomplete);
+    }
+
+    #[test]
+    fn grep_into_pipe() {
+        let (mut reader, writer) = io::pipe().unwrap();
+        let streams = Streams { stdout: Output::Pipe(writer), ..Default::default() };
+        let res = redirect::scoped(streams, || grep_lines("b", Cursor::new("a\nb\nbb\n")).unwrap());
+        // written to the pipe, nothing left for the terminal
+        assert_eq!((0, String::new()), res);
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!("b\nbb\n", text);
+    }
+
+    #[test]
+    fn separate_streams() {
+        let (out, err) = (Buffer::new(), Buffer::new());
+        let streams = Streams {
+            stdout: Output::Buffer(out.clone()),
+            stderr: Output::Buffer(err.clone()),
+            ..Default::default()
+        };
+        redirect::scoped(streams, || {
+            redirect::write_result((0, "ok".to_string()));
+            redirect::write_result((104, "Error: bad".to_string()));
+            let mut sh = std::process::Command::new("sh");
+            sh.args(["-c", "echo a; echo b >&2"]);
+            redirect::spawn(&mut sh).unwrap();
+        });
+        assert_eq!("ok\na\n", out.contents());
+        assert_eq!("Error: bad\nb\n", err.contents());
+    }
+
+    #[test]
+    fn path_lookup() {
+        assert!(find_in_path("sh").is_some());
+        assert!(find_in_path("no-such-program-in-path").is_none());
+        assert!(find_in_path("").is_none());
+    }
+
+    #[test]
+    fn type_and_command_v() {
+        let names = vec!["ls".to_string(), "sudo".to_string()];
+        assert_eq!(
+            (0, "ls is a tiks builtin\nsudo is a tiks builtin".to_string()),
+            type_of(&names, &Functions::new()).unwrap()
+        );
+        assert_eq!((0, "ls".to_string()), command_v(&names[..1], &Functions::new()).unwrap());
+        assert_eq!(1, command_v(&["no-such-program-in-path".to_string()], &Functions::new()).unwrap().0);
+        assert_eq!("rm is a tiks builtin (root only)", type_of(&["rm".to_string()], &Functions::new()).unwrap().1);
+        // what is found goes to stdout, only the misses come back with the status
+        let names = vec!["ls".to_string(), "no-such-program-in-path".to_string()];
+        assert_eq!(1, type_of(&names, &Functions::new()).unwrap_err().code());
+        assert_eq!((1, String::new()), command_v(&names, &Functions::new()).unwrap());
+        assert_eq!(1, which(false, &["sh".to_string(), names[1].clone()]).unwrap_err().code());
+    }
+
+    #[test]
+    fn registry_lookup() {
+        // alias, priority and role come from one registration
+        assert_eq!("ls", registry::find("l").unwrap().name());
+        assert_eq!(CommandPriority::High, get_priority("sleep"));
+        assert!(registry::root_names().contains(&"rm".to_string()));
+        assert!(get_similar("lss", &Functions::new(), &Aliases::new()).contains(&"ls".to_string()));
+    }
+
+    #[test]
+    fn spec_parse() {
+        let words = |w: &str| w.split(' ').map(String::from).collect::<Vec<_>>();
+        let spec = Spec::new()
+            .flag('l', "long", "")
+            .flag('a', "all", "")
+            .option('f', "fix", "VALUE", "")
+            .arg("NAME")
+            .optional_int("N");
+
+        let args = spec.parse("t", &words("-la --fix=x name 3")).unwrap();
+        assert!(args.has("l") && args.has("all"));
+        assert_eq!(Some("x"), args.value("f"));
+        assert_eq!(vec!["name", "3"], args.values);
+        // -fVALUE, the old -long form, flags stop at the first argument
+        let args = spec.parse("t", &words("-fy -all name")).unwrap();
+        assert_eq!((Some("y"), true), (args.value("fix"), args.has("a")));
+        assert_eq!(104, spec.parse("t", &words("name -l")).unwrap_err().code());
+
+        assert_eq!(104, spec.parse("t", &words("-q name")).unwrap_err().code());
+        assert_eq!(104, spec.parse("t", &words("name x")).unwrap_err().code());
+        assert!(spec.parse("t", &[]).unwrap_err().to_string().contains("missing NAME"));
+        assert!(spec.parse("t", &words("--help")).unwrap().help);
+        assert_eq!("Usage: t [-la] [-f VALUE] NAME [N]", spec.usage("t"));
+        // no flags: `-p` is unknown, unless the words pass through like echo's
+        assert_eq!(104, Spec::new().many("DIR").parse("t", &words("-p a/b")).unwrap_err().code());
+        assert_eq!(vec!["-n", "x"], Spec::new().pass_through().rest("TEXT").parse("t", &words("-n x")).unwrap().values);
+    }
+
+    #[test]
+    fn help_is_a_word_to_echo_and_test() {
+        let mut session = session();
+        run_script("[ --help = --help ] && A=1; test --help != --help || B=1; E=$(echo --help)", &mut session);
+        assert_eq!(Some("1".to_string()), session.get_var("A"));
+        assert_eq!(Some("1".to_string()), session.get_var("B"));
+        assert_eq!(Some("--help".to_string()), session.get_var("E"));
+    }
+
+    #[test]
+    fn tiks_error_chain() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no file");
+        let err = TiksError::io("copy a to b failed").with_source(io);
+        assert_eq!(Category::Io, err.category());
+        assert_eq!("Error: copy a to b failed: no file", err.to_string());
+        // io errors pick the category from their kind
+        let err: TiksError = std::io::Error::from(std::io::ErrorKind::PermissionDenied).into();
+        assert_eq!((Category::Permission, 401), (err.category(), err.code()));
+        let (status, _): (usize, String) = TiksError::usage("x").into();
+        assert_eq!(2, exit_code(status));
+        // no header without suggestions
+        assert!(!unknown_command("hi", "").to_string().contains("Did you mean?"));
+        assert!(unknown_command("lss", "ls").to_string().ends_with("Did you mean?\nls"));
+    }
+
+    #[test]
+    fn aliases() {
+        let mut aliases = Aliases::new();
+        aliases.insert("ll2".into(), "ls -l | grep rs".into());
+        aliases.insert("ls".into(), "ls -a".into());
+        aliases.insert("loop".into(), "echo 1 | loop".into());
+        let list = parse_with("ll2 src", &aliases).unwrap();
+        let pipeline = &list.items[0].and_or.first.commands;
+        assert_eq!(2, pipeline.len());
+        assert_eq!("grep", pipeline[1].name());
+        // an alias is not expanded again inside its own text
+        let list = parse_with("loop", &aliases).unwrap();
+        assert_eq!("loop", list.items[0].and_or.first.commands[1].name());
+        // only the first word of a command
+        assert!(parse_with("echo ll2", &aliases).unwrap().items[0].and_or.first.commands.len() == 1);
+
+        aliases.insert("q".into(), "echo 'it'\''s'".into());
+        let file = temp_path("aliases");
+        alias::save_to(&file, &aliases).unwrap();
+        assert_eq!(aliases, alias::load_from(&file));
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn source_file() {
+        let mut session = session();
+        session.args = vec!["outer".into()];
+        session.shell_name = "script.tks".into();
+        let file = temp_path("source");
+        std::fs::write(&file, "FROM_FILE=$1\nZERO=$0\nalias hh='echo hh'\nreturn 3\nAFTER=1\n").unwrap();
+        let path = file.to_string_lossy().to_string();
+        assert_eq!(3, source(&[path.clone(), "inner".into()], &mut session).unwrap().0);
+        assert_eq!(Some("inner".to_string()), session.get_var("FROM_FILE"));
+        // $0 is still the script that sources
+        assert_eq!(Some("script.tks".to_string()), session.get_var("ZERO"));
+        assert_eq!(None, session.get_var("AFTER"));
+        assert!(session.aliases.contains_key("hh"));
+        // the arguments of the caller come back
+        assert_eq!(vec!["outer".to_string()], session.args);
+        std::fs::remove_file(&file).unwrap();
+        assert!(source(&[path], &mut session).is_err());
+    }
+
+    #[test]
+    fn config_keys() {
+        let defaults = Config::default();
+        assert!(defaults.check().is_ok());
+        assert_eq!(2, defaults.run.semaphore);
+        assert!(defaults.root.commands.contains(&"rm".to_string()));
+
+        let changed = config::set(&defaults, "run.semaphore", "4").unwrap();
+        assert_eq!(4, changed.run.semaphore);
+        // a plain number is a color too
+        assert_eq!("31", config::set(&changed, "prompt.user", "31").unwrap().prompt.user);
+        assert!(config::set(&changed, "run.semaphores", "4").unwrap_err().to_string().contains("unknown key"));
+        assert!(config::set(&changed, "run.semaphore", "0").is_err());
+        assert!(config::set(&changed, "root.commands", "[\"nope\"]").is_err());
+        let htop = config::set(&changed, "apt.packages.htop", "{ version = \"3\", url = \"https://example.com/htop.tgz\" }").unwrap();
+        assert_eq!("3", htop.apt.packages["htop"].version);
+        assert!(config::list(&htop).contains(&"run.semaphore = 4".to_string()));
+
+        // only the key set goes to the file
+        let file = temp_path("config");
+        std::fs::write(&file, "[suggest]\nthreshold = 3\n").unwrap();
+        config::save_key(&file, "run.semaphore", config::get(&changed, "run.semaphore").unwrap()).unwrap();
+        config::save_key(&file, "apt.packages", config::get(&htop, "apt.packages").unwrap()).unwrap();
+        let saved = config::load_from(&file).unwrap();
+        assert_eq!((3, 4, "3"), (saved.suggest.threshold, saved.run.semaphore, saved.apt.packages["htop"].version.as_str()));
+        assert!(saved.apt.packages.contains_key("tree"));
+        assert!(!std::fs::read_to_string(&file).unwrap().contains("commands"));
+        std::fs::write(&file, "[suggest]\nthreshold = 1\ncolor = 2\n").unwrap();
+        assert!(config::load_from(&file).unwrap_err().to_string().contains("unknown field `color`"));
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn file_errors_fail() {
+        let dir = temp_path("rm");
+        let path = dir.to_string_lossy().to_string();
+        mkdir(&format!("{}/inner", path)).unwrap();
+        // not empty
+        assert_ne!(0, rm(&path).unwrap_err().code());
+        assert!(rename(&format!("{}/none", path), &format!("{}/other", path)).is_err());
+        rm(&format!("{}/inner", path)).unwrap();
+        rm(&path).unwrap();
+        assert!(rm(&path).is_err());
+    }
+
+    #[test]
+    fn root_only_everywhere() {
+        let mut session = session();
+        for line in ["cat nofile", "command cat nofile", "env cat nofile", "env X=1 rm nofile", "cat() { echo; }; command cat nofile", "config set root.commands []"] {
+            assert_eq!(PERMISSION, run_script(line, &mut session), "{}", line);
+        }
+        // a function of the same name is skipped
+        run_script("expr() { X=fn; }; command expr 1", &mut session);
+        assert_eq!(None, session.get_var("X"));
+
+        // a command registered later is checked by its role, and `type` says the same
+        struct Guarded;
+        impl registry::Command for Guarded {
+            fn name(&self) -> &str { "tiks_guarded" }
+            fn role(&self) -> UserRole { UserRole::Admin }
+            fn help(&self) -> &str { "" }
+            fn execute(&self, _: &Args, _: &mut SessionContext) -> TiksResult { Ok((0, String::new())) }
+        }
+        registry::register(std::sync::Arc::new(Guarded));
+        assert_eq!(PERMISSION, run_script("tiks_guarded", &mut session));
+        assert_eq!("tiks_guarded is a tiks builtin (root only)", type_of(&["tiks_guarded".to_string()], &session.functions).unwrap().1);
+    }
+
+    #[test]
+    fn status_to_exit_code() {
+        assert_eq!(0, exit_code(0));
+        assert_eq!(2, exit_code(104));
+        assert_eq!(127, exit_code(403));
+        assert_eq!(126, exit_code(401));
+        assert_eq!(42, exit_code(42));
+        assert_eq!(1, exit_code(1000));
+        // the exit code of a program stays as it is
+        assert_eq!(3, raw_status(3));
+        assert_eq!(104, exit_code(raw_status(104)));
+        assert_eq!(104, status_value(raw_status(104)));
+        let mut session = session();
+        assert_eq!(104, exit_code(run_script("sh -c 'exit 104'", &mut session)));
+        run_script("f() { return 106; }; f; X=$?", &mut session);
+        assert_eq!(Some("106".to_string()), session.get_var("X"));
     }
 
     #[test]